@@ -0,0 +1,482 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use ggez::Context;
+use ggez::conf::NumSamples;
+use ggez::graphics::{self, BlendMode, Canvas, Color, DrawParam, Drawable, Image, Rect};
+use crate::Result;
+
+/// Size of atlas pages in logical units. Textures that don't fit get a page
+/// of their own.
+const PAGE_SIZE: f32 = 2048.0;
+/// Largest page in pixels. Many GL drivers don't go past 4096, so at high
+/// resolutions pages get smaller in logical units and there are more of
+/// them.
+const MAX_PAGE_PIXELS: f32 = 4096.0;
+// Space left around each texture, so that filtering at the edges does not
+// pick up pixels from its neighbours.
+const PADDING: f32 = 2.0;
+
+/// A texture that is drawn by the game rather than loaded, so it can be
+/// drawn again at a new resolution when the window size changes.
+pub trait TextureSource {
+    /// Draws the texture in its own logical coordinates, starting at (0, 0).
+    /// Text should be rasterised at `resolution` times its logical size.
+    fn draw(&self, ctx: &mut Context, resolution: f32) -> Result;
+
+    /// A hash of everything the texture is drawn from. Textures with a key
+    /// are saved to the atlas cache and loaded from there while the key
+    /// stays the same.
+    fn cache_key(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// A texture in a `TextureAtlas`. Where it ends up depends on the
+/// resolution the atlas was last rendered at.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Texture {
+    index: u32,
+}
+
+enum Content {
+    Image(Image),
+    Source(Box<dyn TextureSource>),
+}
+
+struct Entry {
+    width: f32,
+    height: f32,
+    content: Content,
+}
+
+/// A region of an atlas page, in the page's logical units.
+#[derive(Copy, Clone)]
+struct Placement {
+    page: u32,
+    region: Rect,
+}
+
+struct Page {
+    width: f32,
+    height: f32,
+    shelf_x: f32,
+    shelf_y: f32,
+    shelf_height: f32,
+}
+
+/// Places textures onto pages of at most `page_size` logical units,
+/// putting them in rows. Returns the used size of every page and where
+/// each texture went.
+fn pack(entries: &[Entry], page_size: f32) -> (Vec<(f32, f32)>, Vec<Placement>) {
+    let mut pages: Vec<Page> = Vec::new();
+    let mut placements = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let (width, height) = (entry.width, entry.height);
+        let fits_shelf = |page: &Page| {
+            page.shelf_x + width + PADDING <= page.width
+                && page.shelf_y + height + PADDING <= page.height
+        };
+        let fits_below = |page: &Page| {
+            width + PADDING * 2.0 <= page.width
+                && page.shelf_y + page.shelf_height + height + PADDING * 2.0 <= page.height
+        };
+        match pages.last_mut() {
+            Some(page) if fits_shelf(page) => {}
+            Some(page) if fits_below(page) => {
+                page.shelf_x = PADDING;
+                page.shelf_y += page.shelf_height + PADDING;
+                page.shelf_height = 0.0;
+            }
+            _ => pages.push(Page {
+                width: page_size.max(width + PADDING * 2.0),
+                height: page_size.max(height + PADDING * 2.0),
+                shelf_x: PADDING,
+                shelf_y: PADDING,
+                shelf_height: 0.0,
+            }),
+        }
+        let index = pages.len() - 1;
+        let page = &mut pages[index];
+        let region = Rect::new(page.shelf_x, page.shelf_y, width, height);
+        page.shelf_x += width + PADDING;
+        page.shelf_height = page.shelf_height.max(height);
+        placements.push(Placement { page: index as u32, region });
+    }
+    let sizes = pages
+        .iter()
+        // The unused bottom of a page is cut off.
+        .map(|p| (p.width, p.shelf_y + p.shelf_height + PADDING))
+        .collect();
+    (sizes, placements)
+}
+
+/// Collects textures for a `TextureAtlas`.
+#[derive(Default)]
+pub struct AtlasBuilder {
+    entries: Vec<Entry>,
+    cache: Option<PathBuf>,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder::default()
+    }
+
+    /// Keeps rendered textures in `dir`, see `TextureSource::cache_key`.
+    pub fn with_cache(mut self, dir: PathBuf) -> AtlasBuilder {
+        self.cache = Some(dir);
+        self
+    }
+
+    /// Adds a loaded image, with its logical size being its size in pixels.
+    pub fn add_image(&mut self, image: Image) -> Texture {
+        let (width, height) = (f32::from(image.width()), f32::from(image.height()));
+        self.add(width, height, Content::Image(image))
+    }
+
+    /// Adds a texture of the given logical size drawn by `source`.
+    pub fn add_source(&mut self, width: f32, height: f32, source: impl TextureSource + 'static) -> Texture {
+        self.add(width, height, Content::Source(Box::new(source)))
+    }
+
+    fn add(&mut self, width: f32, height: f32, content: Content) -> Texture {
+        let texture = Texture { index: self.entries.len() as u32 };
+        self.entries.push(Entry { width, height, content });
+        texture
+    }
+
+    /// Draws all pages at resolution 1.
+    pub fn build(self, ctx: &mut Context) -> Result<TextureAtlas> {
+        let mut atlas = TextureAtlas {
+            sizes: Vec::new(),
+            placements: Vec::new(),
+            pages: Vec::new(),
+            entries: self.entries,
+            cache: self.cache,
+        };
+        if let Some(cache) = &atlas.cache {
+            let keys = atlas.entries
+                .iter()
+                .filter_map(|entry| match &entry.content {
+                    Content::Source(source) => source.cache_key(),
+                    Content::Image(_) => None,
+                })
+                .collect();
+            prune_cache(cache, &keys);
+        }
+        atlas.render(ctx, 1.0)?;
+        Ok(atlas)
+    }
+}
+
+/// Textures packed into a few large images, so that drawing them needs
+/// fewer texture switches.
+pub struct TextureAtlas {
+    sizes: Vec<(f32, f32)>,
+    placements: Vec<Placement>,
+    pages: Vec<Image>,
+    entries: Vec<Entry>,
+    cache: Option<PathBuf>,
+}
+
+impl TextureAtlas {
+    /// Draws every page again at `resolution` times its logical size. This
+    /// presents the frame, so the screen has to be set up again afterwards.
+    pub(crate) fn render(&mut self, ctx: &mut Context, resolution: f32) -> Result {
+        let (sizes, placements) = pack(&self.entries, PAGE_SIZE.min(MAX_PAGE_PIXELS / resolution));
+        let mut pages = Vec::with_capacity(sizes.len());
+        for (index, &size) in sizes.iter().enumerate() {
+            let entries = self.entries
+                .iter()
+                .zip(&placements)
+                .filter(|(_, placement)| placement.page as usize == index)
+                .map(|(entry, placement)| (entry, placement.region))
+                .collect::<Vec<_>>();
+            pages.push(render_page(ctx, size, &entries, resolution, self.cache.as_deref())?);
+        }
+        self.sizes = sizes;
+        self.placements = placements;
+        self.pages = pages;
+        Ok(())
+    }
+
+    /// The page that the texture is on, for batching by page.
+    pub(crate) fn page_index(&self, texture: Texture) -> u32 {
+        self.placements[texture.index as usize].page
+    }
+
+    pub(crate) fn page(&self, index: u32) -> &Image {
+        &self.pages[index as usize]
+    }
+
+    /// The part of the page image covered by the texture, in the 0 to 1
+    /// range used by `DrawParam::src`.
+    pub(crate) fn uv(&self, texture: Texture) -> Rect {
+        let Placement { page, region } = self.placements[texture.index as usize];
+        let (width, height) = self.sizes[page as usize];
+        Rect::new(
+            region.x / width,
+            region.y / height,
+            region.w / width,
+            region.h / height,
+        )
+    }
+}
+
+fn render_page(
+    ctx: &mut Context,
+    (width, height): (f32, f32),
+    entries: &[(&Entry, Rect)],
+    resolution: f32,
+    cache: Option<&Path>,
+) -> Result<Image> {
+    let canvas = Canvas::new(
+        ctx,
+        (width * resolution).round() as u16,
+        (height * resolution).round() as u16,
+        NumSamples::One,
+    )?;
+    // Cached textures are drawn from their files, the rest are saved there
+    // once the page is done.
+    let mut uncached = Vec::new();
+    let mut cached = Vec::new();
+    for &(entry, region) in entries {
+        let path = match (&entry.content, cache) {
+            (Content::Source(source), Some(cache)) => source
+                .cache_key()
+                .map(|key| cache_path(cache, key, pixel_bounds(region, resolution))),
+            _ => None,
+        };
+        match path {
+            Some(path) => match load_cached(ctx, &path) {
+                Some(image) => cached.push(Some(image)),
+                None => {
+                    uncached.push((region, path));
+                    cached.push(None);
+                }
+            },
+            None => cached.push(None),
+        }
+    }
+    // Sources are drawn onto their own canvases first, so that anything
+    // reaching outside their region is cut off instead of ending up in a
+    // neighbouring texture.
+    let mut drawn = Vec::new();
+    for (&(entry, region), cached) in entries.iter().zip(&cached) {
+        if let (Content::Source(source), None) = (&entry.content, cached) {
+            drawn.push(Some(render_entry(ctx, source.as_ref(), region, resolution)?));
+        } else {
+            drawn.push(None);
+        }
+    }
+    graphics::set_canvas(ctx, Some(&canvas));
+    graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+    graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height))?;
+    for ((&(entry, region), cached), drawn) in entries.iter().zip(cached).zip(&mut drawn) {
+        // Regions don't overlap, so the pixels are copied as they are. That
+        // way a texture comes out the same whether it was just rendered or
+        // loaded from the cache.
+        let (x, y, width, height) = pixel_bounds(region, resolution);
+        let dest = [x as f32 / resolution, y as f32 / resolution];
+        if let Some(drawn) = drawn {
+            drawn.set_blend_mode(Some(BlendMode::Replace));
+            let draw = DrawParam::new()
+                .dest(dest)
+                .scale([1.0 / resolution, 1.0 / resolution]);
+            graphics::draw(ctx, &*drawn, draw)?;
+            continue;
+        }
+        let mut image = match (&entry.content, cached) {
+            (_, Some(image)) => image,
+            (Content::Image(image), None) => image.clone(),
+            (Content::Source(_), None) => continue,
+        };
+        image.set_blend_mode(Some(BlendMode::Replace));
+        let draw = DrawParam::new()
+            .dest(dest)
+            .scale([
+                width as f32 / f32::from(image.width()) / resolution,
+                height as f32 / f32::from(image.height()) / resolution,
+            ]);
+        graphics::draw(ctx, &image, draw)?;
+    }
+    graphics::present(ctx)?;
+    graphics::set_canvas(ctx, None);
+    let (page_width, page_height, data) = read_canvas(ctx, canvas)?;
+    for (region, path) in uncached {
+        if let Err(e) = save_cached(&data, page_width, pixel_bounds(region, resolution), &path) {
+            eprintln!("could not cache texture at {}: {}", path.display(), e);
+        }
+    }
+    Image::from_rgba8(ctx, page_width, page_height, &data)
+}
+
+fn render_entry(ctx: &mut Context, source: &dyn TextureSource, region: Rect, resolution: f32) -> Result<Canvas> {
+    let (_, _, width, height) = pixel_bounds(region, resolution);
+    let canvas = Canvas::new(ctx, width.max(1) as u16, height.max(1) as u16, NumSamples::One)?;
+    graphics::set_canvas(ctx, Some(&canvas));
+    graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+    graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, region.w, region.h))?;
+    source.draw(ctx, resolution)?;
+    Ok(canvas)
+}
+
+/// Pixels covered by a region as (x, y, width, height).
+fn pixel_bounds(region: Rect, resolution: f32) -> (u32, u32, u32, u32) {
+    let x = (region.x * resolution).round() as u32;
+    let y = (region.y * resolution).round() as u32;
+    let right = ((region.x + region.w) * resolution).round() as u32;
+    let bottom = ((region.y + region.h) * resolution).round() as u32;
+    (x, y, right - x, bottom - y)
+}
+
+fn cache_path(cache: &Path, key: u64, (_, _, width, height): (u32, u32, u32, u32)) -> PathBuf {
+    cache.join(format!("{:016x}-{}x{}.png", key, width, height))
+}
+
+/// Deletes cached textures whose key is no longer used by any texture, as
+/// every change to a card leaves its old renders behind.
+fn prune_cache(cache: &Path, keys: &HashSet<u64>) {
+    let entries = match std::fs::read_dir(cache) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let key = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| name.ends_with(".png"))
+            .and_then(|name| name.split('-').next())
+            .and_then(|key| u64::from_str_radix(key, 16).ok());
+        match key {
+            Some(key) if !keys.contains(&key) => {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    eprintln!("could not delete cached texture {}: {}", path.display(), e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn load_cached(ctx: &mut Context, path: &Path) -> Option<Image> {
+    if !path.is_file() {
+        return None;
+    }
+    let image = match image::open(path) {
+        Ok(image) => image.to_rgba(),
+        Err(e) => {
+            eprintln!("could not load cached texture {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let (width, height) = image.dimensions();
+    match Image::from_rgba8(ctx, width as u16, height as u16, &image) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            eprintln!("could not load cached texture {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn save_cached(
+    page: &[u8],
+    page_width: u16,
+    (x, y, width, height): (u32, u32, u32, u32),
+    path: &Path,
+) -> std::io::Result<()> {
+    let row = usize::from(page_width) * 4;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for line in page.chunks(row).skip(y as usize).take(height as usize) {
+        pixels.extend_from_slice(&line[x as usize * 4..(x + width) as usize * 4]);
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    image::save_buffer(path, &pixels, width, height, image::RGBA(8))
+}
+
+/// Draws `source` on its own at `resolution` times its logical size and
+/// reads back the pixels, for saving the texture outside of the game. The
+/// screen has to be set up again afterwards.
+pub fn render_pixels(
+    ctx: &mut Context,
+    source: &dyn TextureSource,
+    width: f32,
+    height: f32,
+    resolution: f32,
+) -> Result<image::RgbaImage> {
+    let canvas = Canvas::new(
+        ctx,
+        (width * resolution).round() as u16,
+        (height * resolution).round() as u16,
+        NumSamples::One,
+    )?;
+    graphics::set_canvas(ctx, Some(&canvas));
+    graphics::clear(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
+    graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height))?;
+    source.draw(ctx, resolution)?;
+    graphics::present(ctx)?;
+    graphics::set_canvas(ctx, None);
+    let (width, height, data) = read_canvas(ctx, canvas)?;
+    Ok(image::RgbaImage::from_raw(u32::from(width), u32::from(height), data)
+        .expect("canvas data does not match its size"))
+}
+
+/// Reads back canvas contents as (width, height, pixels).
+fn read_canvas(ctx: &mut Context, canvas: Canvas) -> Result<(u16, u16, Vec<u8>)> {
+    read_image(ctx, &canvas.into_inner())
+}
+
+/// Reads back image contents as (width, height, pixels). Images drawn to
+/// by the GPU come out upside down, so the rows are flipped into a regular
+/// image.
+pub(crate) fn read_image(ctx: &mut Context, image: &Image) -> Result<(u16, u16, Vec<u8>)> {
+    let mut data = image.to_rgba8(ctx)?;
+    let row = usize::from(image.width()) * 4;
+    let half = usize::from(image.height()) / 2;
+    let (first, second) = data.split_at_mut(row * half);
+    // With an odd height the middle row stays where it is.
+    let second = &mut second[row * (usize::from(image.height()) - half * 2)..];
+    let first = first.chunks_mut(row);
+    let second = second.chunks_mut(row);
+    for (a, b) in first.zip(second.rev()) {
+        a.swap_with_slice(b);
+    }
+    Ok((image.width(), image.height(), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Blank;
+
+    impl TextureSource for Blank {
+        fn draw(&self, _ctx: &mut Context, _resolution: f32) -> Result {
+            Ok(())
+        }
+    }
+
+    fn cards(count: usize) -> Vec<Entry> {
+        (0..count)
+            .map(|_| Entry { width: 320.0, height: 448.0, content: Content::Source(Box::new(Blank)) })
+            .collect()
+    }
+
+    #[test]
+    fn pages_fit_the_pixel_limit_at_high_resolutions() {
+        let resolution = 2.4;
+        let entries = cards(60);
+        let (sizes, placements) = pack(&entries, PAGE_SIZE.min(MAX_PAGE_PIXELS / resolution));
+        for &(width, height) in &sizes {
+            assert!((width * resolution).round() <= MAX_PAGE_PIXELS);
+            assert!((height * resolution).round() <= MAX_PAGE_PIXELS);
+        }
+        let (low_res_sizes, _) = pack(&entries, PAGE_SIZE);
+        assert!(sizes.len() > low_res_sizes.len());
+        assert_eq!(placements.len(), entries.len());
+    }
+}
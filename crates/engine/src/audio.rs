@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use ggez::Context;
+use ggez::audio::{SoundData, SoundSource, Source};
+use crate::{Result, RESOURCE_DIR};
+
+const MANIFEST_PATH: &str = "/sfx/sounds.ron";
+const CROSSFADE_TIME: f32 = 1.5;
+
+#[derive(Deserialize, Default)]
+struct Manifest {
+    #[serde(default)]
+    effects: HashMap<String, String>,
+    #[serde(default)]
+    music: HashMap<String, String>,
+}
+
+/// Music is decoded from the file as it plays, as a whole track takes a
+/// lot of memory once decoded.
+struct Stream {
+    sink: rodio::Sink,
+    path: PathBuf,
+    looping: bool,
+}
+
+impl Stream {
+    fn open(ctx: &Context, path: &str) -> Result<Stream> {
+        let stream = Stream {
+            sink: rodio::Sink::new(ctx.audio_context.device()),
+            path: Path::new(RESOURCE_DIR).join(path.trim_start_matches('/')),
+            looping: true,
+        };
+        // Tracks fade in, and the sink starts playing as soon as anything
+        // is queued. The next loop is always queued, so that there is no
+        // gap between them.
+        stream.sink.set_volume(0.0);
+        stream.queue()?;
+        stream.queue()?;
+        Ok(stream)
+    }
+
+    fn queue(&self) -> Result {
+        let file = BufReader::new(File::open(&self.path)?);
+        self.sink.append(rodio::Decoder::new(file)?);
+        Ok(())
+    }
+
+    fn update(&mut self) {
+        if self.looping && self.sink.len() < 2 {
+            if let Err(e) = self.queue() {
+                eprintln!("failed to loop music from {}: {}", self.path.display(), e);
+                self.looping = false;
+            }
+        }
+    }
+}
+
+struct Track {
+    name: String,
+    stream: Stream,
+    // How loud the track is relative to the music volume, from 0 to 1.
+    fade: f32,
+}
+
+pub(crate) struct Audio {
+    effects: HashMap<String, SoundData>,
+    music_paths: HashMap<String, String>,
+    playing_effects: Vec<Source>,
+    music: Option<Track>,
+    fading_music: Vec<Track>,
+    music_volume: f32,
+    effects_volume: f32,
+}
+
+impl Audio {
+    pub(crate) fn empty() -> Audio {
+        Audio {
+            effects: HashMap::new(),
+            music_paths: HashMap::new(),
+            playing_effects: Vec::new(),
+            music: None,
+            fading_music: Vec::new(),
+            music_volume: 1.0,
+            effects_volume: 1.0,
+        }
+    }
+
+    /// Loads every sound effect listed in the manifest. Music is only
+    /// loaded when it starts playing.
+    pub(crate) fn load(ctx: &mut Context) -> Result<Audio> {
+        let mut manifest = String::new();
+        ggez::filesystem::open(ctx, MANIFEST_PATH)?.read_to_string(&mut manifest)?;
+        let manifest: Manifest = ron::from_str(&manifest).map_err(|e| {
+            ggez::GameError::ResourceLoadError(format!("invalid sound manifest: {}", e))
+        })?;
+        let mut audio = Audio::empty();
+        for (name, path) in manifest.effects {
+            match SoundData::new(ctx, &path) {
+                Ok(data) => { audio.effects.insert(name, data); }
+                Err(e) => eprintln!("failed to load sound {} from {}: {}", name, path, e),
+            }
+        }
+        audio.music_paths = manifest.music;
+        Ok(audio)
+    }
+
+    pub(crate) fn update(&mut self, dt: f32) {
+        self.playing_effects.retain(|source| !source.stopped());
+        let step = dt / CROSSFADE_TIME;
+        if let Some(track) = &mut self.music {
+            track.fade = (track.fade + step).min(1.0);
+            track.stream.sink.set_volume(track.fade * self.music_volume);
+            track.stream.update();
+        }
+        for track in &mut self.fading_music {
+            track.fade = (track.fade - step).max(0.0);
+            track.stream.sink.set_volume(track.fade * self.music_volume);
+            if track.fade <= 0.0 {
+                track.stream.sink.stop();
+            } else {
+                track.stream.update();
+            }
+        }
+        self.fading_music.retain(|track| track.fade > 0.0);
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.effects_volume
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+        for track in self.music.iter_mut().chain(&mut self.fading_music) {
+            track.stream.sink.set_volume(track.fade * self.music_volume);
+        }
+    }
+
+    pub fn set_effects_volume(&mut self, volume: f32) {
+        self.effects_volume = volume.clamp(0.0, 1.0);
+    }
+
+    fn play_sound(&mut self, ctx: &mut Context, name: &str) -> Result {
+        let data = match self.effects.get(name) {
+            Some(data) => data.clone(),
+            None => return Ok(()),
+        };
+        if self.effects_volume <= 0.0 {
+            return Ok(());
+        }
+        let mut source = Source::from_data(ctx, data)?;
+        source.set_volume(self.effects_volume);
+        source.play()?;
+        self.playing_effects.push(source);
+        Ok(())
+    }
+
+    fn play_music(&mut self, ctx: &mut Context, name: &str) -> Result {
+        if self.music.as_ref().map(|track| track.name.as_str()) == Some(name) {
+            return Ok(());
+        }
+        self.stop_music();
+        let path = match self.music_paths.get(name) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let stream = Stream::open(ctx, path)?;
+        self.music = Some(Track {
+            name: name.to_owned(),
+            stream,
+            fade: 0.0,
+        });
+        Ok(())
+    }
+
+    fn stop_music(&mut self) {
+        if let Some(track) = self.music.take() {
+            self.fading_music.push(track);
+        }
+    }
+}
+
+/// Audio together with the context needed to play it, see `Ctx::audio`.
+pub struct AudioCtx<'a> {
+    pub(crate) ggez: &'a mut Context,
+    pub(crate) audio: &'a mut Audio,
+}
+
+impl<'a> AudioCtx<'a> {
+    /// Plays a sound effect from the manifest. Unknown names are ignored,
+    /// so a missing sound never stops the game.
+    pub fn play_sound(&mut self, name: &str) {
+        if let Err(e) = self.audio.play_sound(self.ggez, name) {
+            eprintln!("failed to play sound {}: {}", name, e);
+        }
+    }
+
+    /// Starts looping a music track from the manifest, crossfading from
+    /// whatever was playing before.
+    pub fn play_music(&mut self, name: &str) {
+        if let Err(e) = self.audio.play_music(self.ggez, name) {
+            eprintln!("failed to play music {}: {}", name, e);
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        self.audio.stop_music();
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.audio.music_volume()
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.audio.effects_volume()
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.audio.set_music_volume(volume);
+    }
+
+    pub fn set_effects_volume(&mut self, volume: f32) {
+        self.audio.set_effects_volume(volume);
+    }
+}
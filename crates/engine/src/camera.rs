@@ -0,0 +1,119 @@
+use ggez::graphics::DrawParam;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::tween::{Ease, Lerp, Tween};
+
+const MOVE_TIME: f32 = 0.8;
+/// Offset of the strongest shake, in screen units.
+const MAX_SHAKE: f32 = 24.0;
+/// How much shake wears off every second.
+const SHAKE_DECAY: f32 = 1.6;
+
+/// A view into world coordinates, drawn through with
+/// `FrameRenderer::push_camera`. At zoom 1 world coordinates are the same
+/// as screen coordinates.
+pub struct Camera {
+    center: Tween<(f32, f32)>,
+    zoom: Tween<f32>,
+    // Center and zoom before the last update, for drawing in between.
+    previous: ((f32, f32), f32),
+    shake: f32,
+    time: f32,
+}
+
+impl Camera {
+    /// A camera showing the world as it is, centered on the screen.
+    pub fn new() -> Camera {
+        Camera {
+            center: Tween::new((SCREEN_WIDTH / 2.0, SCREEN_HEIGHT / 2.0)),
+            zoom: Tween::new(1.0),
+            previous: ((SCREEN_WIDTH / 2.0, SCREEN_HEIGHT / 2.0), 1.0),
+            shake: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Smoothly moves the camera to show `center` in the middle of the
+    /// screen, magnified `zoom` times.
+    pub fn look_at(&mut self, center: (f32, f32), zoom: f32) {
+        if self.center.target() != center {
+            self.center.retarget(center, MOVE_TIME, Ease::SineInOut);
+        }
+        if self.zoom.target() != zoom {
+            self.zoom.retarget(zoom, MOVE_TIME, Ease::SineInOut);
+        }
+    }
+
+    /// Shakes the view, with 1 being the strongest shake. Shakes add up,
+    /// so a few small ones in a row feel like a bigger one.
+    pub fn shake(&mut self, amount: f32) {
+        self.shake = (self.shake + amount).min(1.0);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.previous = (self.center(), self.zoom());
+        self.center.update(dt);
+        self.zoom.update(dt);
+        self.time += dt;
+        self.shake = (self.shake - SHAKE_DECAY * dt).max(0.0);
+    }
+
+    fn shake_offset(&self) -> (f32, f32) {
+        // Squaring makes small shakes subtle while big ones stay strong.
+        let strength = MAX_SHAKE * self.shake * self.shake;
+        // A few unrelated waves add up to something that looks random,
+        // without jumping around like noise would.
+        let t = self.time;
+        let x = (t * 47.0).sin() * 0.6 + (t * 31.0 + 1.3).sin() * 0.4;
+        let y = (t * 43.0 + 0.7).sin() * 0.6 + (t * 29.0 + 2.1).sin() * 0.4;
+        (x * strength, y * strength)
+    }
+
+    /// The world point at the center of the screen, ignoring shake.
+    pub fn center(&self) -> (f32, f32) {
+        self.center.value()
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom.value()
+    }
+
+    pub fn to_screen(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = self.offset();
+        let zoom = self.zoom();
+        (x * zoom + dx, y * zoom + dy)
+    }
+
+    pub fn to_world(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = self.offset();
+        let zoom = self.zoom();
+        ((x - dx) / zoom, (y - dy) / zoom)
+    }
+
+    /// Where the world origin ends up on the screen.
+    fn offset(&self) -> (f32, f32) {
+        self.offset_with(self.center(), self.zoom())
+    }
+
+    fn offset_with(&self, (x, y): (f32, f32), zoom: f32) -> (f32, f32) {
+        let (shake_x, shake_y) = self.shake_offset();
+        (SCREEN_WIDTH / 2.0 - x * zoom + shake_x, SCREEN_HEIGHT / 2.0 - y * zoom + shake_y)
+    }
+
+    /// The view `interpolation` of the way from the last update to the
+    /// current one, see `Ctx::interpolation`.
+    pub(crate) fn transform(&self, interpolation: f32) -> DrawParam {
+        let (center, zoom) = self.previous;
+        let center = center.lerp(self.center(), interpolation);
+        let zoom = zoom.lerp(self.zoom(), interpolation);
+        let (x, y) = self.offset_with(center, zoom);
+        DrawParam::new()
+            .dest([x, y])
+            .scale([zoom, zoom])
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ggez::Context;
+use ggez::event::KeyMods;
+use ggez::input::keyboard::KeyCode;
+use image::{imageops, FilterType, RgbaImage};
+use crate::{Result, GAME_ID};
+
+/// Saves a screenshot, or starts recording a GIF when shift is held.
+const CAPTURE_KEY: KeyCode = KeyCode::F12;
+const RECORD_TIME: f32 = 5.0;
+const GIF_FPS: f32 = 20.0;
+// Recordings are scaled down to this width, as full size frames make
+// files too large to attach anywhere.
+const GIF_WIDTH: u32 = 800;
+
+struct Recording {
+    frames: Vec<RgbaImage>,
+    elapsed: f32,
+    next_frame: f32,
+}
+
+/// Screenshots and GIF recordings of the window, for bug reports and
+/// changelogs.
+#[derive(Default)]
+pub(crate) struct Capture {
+    screenshot: bool,
+    recording: Option<Recording>,
+}
+
+impl Capture {
+    pub(crate) fn key_down(&mut self, key: KeyCode, mods: KeyMods) {
+        if key != CAPTURE_KEY {
+            return;
+        }
+        if !mods.contains(KeyMods::SHIFT) {
+            self.screenshot = true;
+        } else if self.recording.is_none() {
+            println!("recording the next {} seconds", RECORD_TIME);
+            self.recording = Some(Recording {
+                frames: Vec::new(),
+                elapsed: 0.0,
+                next_frame: 0.0,
+            });
+        }
+    }
+
+    /// Captures the frame that has been drawn but not presented yet. `dt`
+    /// is the time since the previous frame.
+    pub(crate) fn frame(&mut self, ctx: &mut Context, dt: f32) {
+        if std::mem::replace(&mut self.screenshot, false) {
+            let path = capture_path("screenshot", "png");
+            match read_screen(ctx).and_then(|image| Ok(save_image(&image, &path)?)) {
+                Ok(()) => println!("saved screenshot to {}", path.display()),
+                Err(e) => eprintln!("failed to save screenshot: {}", e),
+            }
+        }
+        if let Some(recording) = &mut self.recording {
+            if recording.elapsed >= recording.next_frame {
+                match read_screen(ctx) {
+                    Ok(image) => recording.frames.push(shrink(image)),
+                    Err(e) => eprintln!("failed to record frame: {}", e),
+                }
+                recording.next_frame += 1.0 / GIF_FPS;
+            }
+            recording.elapsed += dt;
+            if recording.elapsed >= RECORD_TIME {
+                let frames = std::mem::take(&mut recording.frames);
+                self.recording = None;
+                // Encoding takes a few seconds, which would freeze the game.
+                std::thread::spawn(move || {
+                    let path = capture_path("recording", "gif");
+                    match save_gif(&frames, &path) {
+                        Ok(()) => println!("saved recording to {}", path.display()),
+                        Err(e) => eprintln!("failed to save recording: {}", e),
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn read_screen(ctx: &mut Context) -> Result<RgbaImage> {
+    let screen = ggez::graphics::screenshot(ctx)?;
+    let (width, height, mut data) = crate::atlas::read_image(ctx, &screen)?;
+    // The window has no use for alpha, so whatever is left there would
+    // only make the image partly transparent.
+    for pixel in data.chunks_mut(4) {
+        pixel[3] = 255;
+    }
+    Ok(RgbaImage::from_raw(u32::from(width), u32::from(height), data)
+        .expect("screen data does not match its size"))
+}
+
+fn shrink(image: RgbaImage) -> RgbaImage {
+    if image.width() <= GIF_WIDTH {
+        return image;
+    }
+    let height = image.height() * GIF_WIDTH / image.width();
+    imageops::resize(&image, GIF_WIDTH, height, FilterType::Triangle)
+}
+
+/// Captures go to the user's pictures directory, or the working directory
+/// if there is none.
+fn capture_path(kind: &str, extension: &str) -> PathBuf {
+    let dir = directories::UserDirs::new()
+        .and_then(|dirs| dirs.picture_dir().map(|dir| dir.join(GAME_ID)))
+        .unwrap_or_else(|| PathBuf::from("captures"));
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    dir.join(format!("{}-{}.{}", kind, time, extension))
+}
+
+fn save_image(image: &RgbaImage, path: &std::path::Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    image.save(path)
+}
+
+fn save_gif(frames: &[RgbaImage], path: &std::path::Path) -> std::io::Result<()> {
+    let (width, height) = match frames.first() {
+        Some(frame) => (frame.width() as u16, frame.height() as u16),
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+    encoder.write_extension(gif::ExtensionData::Repetitions(gif::Repeat::Infinite))?;
+    for frame in frames {
+        let pixels = frame.pixels().flat_map(|p| p.0[..3].to_vec()).collect::<Vec<_>>();
+        // Speed trades palette quality for encoding time, 10 is the
+        // default of `from_rgba`.
+        let mut frame = gif::Frame::from_rgb_speed(width, height, &pixels, 10);
+        // In hundredths of a second.
+        frame.delay = (100.0 / GIF_FPS).round() as u16;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use ggez::graphics::Color;
+use rand::Rng;
+use crate::Icon;
+use crate::text::ScreenText;
+use crate::tween::Ease;
+
+const TEXT_SIZE: f32 = 36.0;
+const TEXT_RISE_SPEED: f32 = 60.0;
+const TEXT_LIFETIME: f32 = 1.0;
+
+/// How the particles of a burst look and move.
+#[derive(Debug, Copy, Clone)]
+pub struct Burst {
+    pub icon: Icon,
+    pub count: u32,
+    /// Fastest initial speed, each particle gets a random speed up to this.
+    pub speed: f32,
+    /// Vertical acceleration, negative values make particles float up.
+    pub gravity: f32,
+    pub size: f32,
+    pub lifetime: f32,
+}
+
+struct Particle {
+    icon: Icon,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    gravity: f32,
+    size: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+pub(crate) struct FloatingText {
+    pub(crate) text: ScreenText,
+    pub(crate) position: (f32, f32),
+    pub(crate) color: Color,
+    age: f32,
+}
+
+/// Short lived particles and floating text, drawn with
+/// `FrameRenderer::draw_effects`.
+pub struct Effects {
+    particles: Vec<Particle>,
+    texts: Vec<FloatingText>,
+}
+
+impl Effects {
+    pub fn new() -> Effects {
+        Effects {
+            particles: Vec::new(),
+            texts: Vec::new(),
+        }
+    }
+
+    /// Throws particles out from `position` in random directions.
+    pub fn burst(&mut self, burst: Burst, position: (f32, f32)) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..burst.count {
+            let angle = rng.gen::<f32>() * std::f32::consts::PI * 2.0;
+            let speed = burst.speed * (0.3 + rng.gen::<f32>() * 0.7);
+            let lifetime = burst.lifetime * (0.7 + rng.gen::<f32>() * 0.3);
+            self.particles.push(Particle {
+                icon: burst.icon,
+                position,
+                velocity: (angle.cos() * speed, angle.sin() * speed),
+                gravity: burst.gravity,
+                size: burst.size,
+                age: 0.0,
+                lifetime,
+            });
+        }
+    }
+
+    /// Shows text centered on `position` that rises and fades out.
+    pub fn float_text(&mut self, text: &str, position: (f32, f32), color: Color) {
+        self.texts.push(FloatingText {
+            text: ScreenText::new(text, TEXT_SIZE),
+            position,
+            color,
+            age: 0.0,
+        });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity.1 += particle.gravity * dt;
+            particle.position.0 += particle.velocity.0 * dt;
+            particle.position.1 += particle.velocity.1 * dt;
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+        for text in &mut self.texts {
+            text.age += dt;
+            text.position.1 -= TEXT_RISE_SPEED * dt;
+        }
+        self.texts.retain(|t| t.age < TEXT_LIFETIME);
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.texts.clear();
+    }
+
+    /// Particles as (icon, x, y, size, opacity), with the position being
+    /// the top left corner.
+    pub(crate) fn particles(&self) -> impl Iterator<Item = (Icon, f32, f32, f32, f32)> + '_ {
+        self.particles.iter().map(|p| {
+            let t = p.age / p.lifetime;
+            let size = p.size * (1.0 - Ease::QuadIn.apply(t) * 0.5);
+            let opacity = 1.0 - Ease::QuadIn.apply(t);
+            (p.icon, p.position.0 - size / 2.0, p.position.1 - size / 2.0, size, opacity)
+        })
+    }
+
+    pub(crate) fn texts(&mut self) -> impl Iterator<Item = (&mut FloatingText, f32)> {
+        self.texts.iter_mut().map(|t| {
+            let opacity = 1.0 - Ease::CubicIn.apply(t.age / TEXT_LIFETIME);
+            (t, opacity)
+        })
+    }
+}
+
+impl Default for Effects {
+    fn default() -> Effects {
+        Effects::new()
+    }
+}
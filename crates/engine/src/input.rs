@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use ggez::event::{Axis, Button};
+use crate::KeyCode;
+
+const STICK_THRESHOLD: f32 = 0.5;
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+    Pause,
+    NextCard,
+    PreviousCard,
+    StartWalk,
+    OpenDeck,
+    OpenDiscards,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub enum InputButton {
+    Key(KeyCode),
+    Gamepad(Button),
+}
+
+pub struct InputMap {
+    bindings: Vec<(InputButton, Action)>,
+}
+
+impl InputMap {
+    pub fn new() -> InputMap {
+        InputMap { bindings: Vec::new() }
+    }
+
+    pub fn bind(&mut self, button: InputButton, action: Action) {
+        self.bindings.push((button, action));
+    }
+}
+
+// There is no keyboard key for `Back`, games bind their own as it is
+// usually rebindable.
+impl Default for InputMap {
+    fn default() -> InputMap {
+        use self::Action::*;
+        use self::InputButton::{Gamepad, Key};
+        InputMap {
+            bindings: vec![
+                (Key(KeyCode::Up), Up),
+                (Key(KeyCode::Down), Down),
+                (Key(KeyCode::Left), Left),
+                (Key(KeyCode::Right), Right),
+                (Key(KeyCode::Return), Confirm),
+                (Key(KeyCode::Q), PreviousCard),
+                (Key(KeyCode::E), NextCard),
+                (Gamepad(Button::DPadUp), Up),
+                (Gamepad(Button::DPadDown), Down),
+                (Gamepad(Button::DPadLeft), Left),
+                (Gamepad(Button::DPadRight), Right),
+                (Gamepad(Button::South), Confirm),
+                (Gamepad(Button::East), Back),
+                (Gamepad(Button::Start), Pause),
+                (Gamepad(Button::LeftTrigger), PreviousCard),
+                (Gamepad(Button::RightTrigger), NextCard),
+                (Gamepad(Button::North), StartWalk),
+                (Gamepad(Button::West), OpenDeck),
+                (Gamepad(Button::Select), OpenDiscards),
+            ],
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct InputState {
+    map: InputMap,
+    gamepad_buttons: HashSet<Button>,
+    // The left stick is treated as a second d-pad.
+    stick_buttons: HashSet<Button>,
+    old_actions: HashSet<Action>,
+    current_actions: HashSet<Action>,
+}
+
+impl InputState {
+    pub(crate) fn set_map(&mut self, map: InputMap) {
+        self.map = map;
+    }
+
+    pub(crate) fn button_down(&mut self, button: Button) {
+        self.gamepad_buttons.insert(button);
+    }
+
+    pub(crate) fn button_up(&mut self, button: Button) {
+        self.gamepad_buttons.remove(&button);
+    }
+
+    pub(crate) fn axis(&mut self, axis: Axis, value: f32) {
+        let (negative, positive) = match axis {
+            Axis::LeftStickX => (Button::DPadLeft, Button::DPadRight),
+            Axis::LeftStickY => (Button::DPadDown, Button::DPadUp),
+            _ => return,
+        };
+        self.stick_buttons.remove(&negative);
+        self.stick_buttons.remove(&positive);
+        if value <= -STICK_THRESHOLD {
+            self.stick_buttons.insert(negative);
+        } else if value >= STICK_THRESHOLD {
+            self.stick_buttons.insert(positive);
+        }
+    }
+
+    pub(crate) fn update(&mut self, keys: &HashSet<KeyCode>) {
+        let mut actions = HashSet::new();
+        for &(button, action) in &self.map.bindings {
+            let pressed = match button {
+                InputButton::Key(key) => keys.contains(&key),
+                InputButton::Gamepad(button) => {
+                    self.gamepad_buttons.contains(&button) || self.stick_buttons.contains(&button)
+                }
+            };
+            if pressed {
+                actions.insert(action);
+            }
+        }
+        self.old_actions = std::mem::replace(&mut self.current_actions, actions);
+    }
+
+    pub(crate) fn is_click(&self, action: Action) -> bool {
+        !self.old_actions.contains(&action) && self.current_actions.contains(&action)
+    }
+
+    pub(crate) fn is_pressed(&self, action: Action) -> bool {
+        self.current_actions.contains(&action)
+    }
+}
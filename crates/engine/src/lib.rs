@@ -1,8 +1,30 @@
+mod atlas;
+mod audio;
+mod camera;
+mod capture;
+mod effects;
+mod input;
 mod renderer;
+mod text;
+pub mod tween;
 
+use std::collections::HashSet;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 pub use ggez;
+pub use image;
+pub use ggez::input::keyboard::KeyCode;
 use ggez::{Context, GameResult, event, graphics, timer};
-pub use crate::renderer::{FrameRenderer, Icon, Renderer, Texture, Textures};
+pub use crate::atlas::{render_pixels, AtlasBuilder, Texture, TextureAtlas, TextureSource};
+pub use crate::audio::AudioCtx;
+pub use crate::camera::Camera;
+pub use crate::effects::{Burst, Effects};
+pub use crate::input::{Action, InputButton, InputMap};
+pub use crate::renderer::{FrameRenderer, Icon, Renderer, Textures};
+pub use crate::text::{Fonts, ScreenText};
+use crate::audio::Audio;
+use crate::capture::Capture;
+use crate::input::InputState;
 
 pub type Error = ggez::GameError;
 pub type Result<T = (), E = Error> = std::result::Result<T, E>;
@@ -10,19 +32,130 @@ pub type Result<T = (), E = Error> = std::result::Result<T, E>;
 pub const SCREEN_WIDTH: f32 = 1600.0;
 pub const SCREEN_HEIGHT: f32 = 900.0;
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+const BACKGROUND: graphics::Color = graphics::WHITE;
+
+/// Games are updated at this fixed rate, whatever the frame rate is.
+pub const UPDATES_PER_SECOND: u32 = 60;
+pub const UPDATE_DT: f32 = 1.0 / UPDATES_PER_SECOND as f32;
+// If the game falls further behind than this, the extra time is dropped
+// instead of trying to catch up.
+const MAX_UPDATES_PER_FRAME: u32 = 5;
+
+const GAME_ID: &str = "ccg";
+const AUTHOR: &str = "domantas";
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum WindowMode {
     Windowed,
+    // Settings saved with the old fullscreen mode, which looked the same.
+    #[serde(alias = "Fullscreen")]
     Borderless,
 }
 
+#[derive(Debug, Clone)]
+pub struct WindowSettings {
+    pub width: f32,
+    pub height: f32,
+    pub mode: WindowMode,
+    pub monitor: Option<String>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: DEFAULT_PHYSICAL_WIDTH,
+            height: DEFAULT_PHYSICAL_HEIGHT,
+            mode: WindowMode::Windowed,
+            monitor: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub name: String,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Monitors that don't report a name are told apart by their position in
+/// the monitor list.
+fn monitor_name(index: usize, monitor: &winit::MonitorId) -> String {
+    monitor
+        .get_name()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("Monitor {}", index + 1))
+}
+
+impl Monitor {
+    fn from_id(index: usize, monitor: &winit::MonitorId) -> Monitor {
+        let size = monitor.get_dimensions();
+        Monitor {
+            name: monitor_name(index, monitor),
+            width: size.width as f32,
+            height: size.height as f32,
+        }
+    }
+}
+
+/// Same directory that ggez uses for its user config, but available
+/// before the context is created.
+pub fn user_config_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", AUTHOR, GAME_ID)
+        .map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// Directory for files that can be created again if they are deleted.
+pub fn user_cache_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", AUTHOR, GAME_ID)
+        .map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
 struct CtxData {
     window_size: (f32, f32),
     physical_window_size: (f32, f32),
     mode: WindowMode,
+    monitor: Option<String>,
     old_mouse_press: bool,
     current_mouse_press: bool,
+    old_mouse_position: (f32, f32),
+    current_mouse_position: (f32, f32),
+    old_keys: HashSet<KeyCode>,
+    current_keys: HashSet<KeyCode>,
+    // Characters typed since the last update, and the ones that the last
+    // update got.
+    pending_text: String,
+    text_input: String,
+    input: InputState,
     scroll_position: (f32, f32),
+    interpolation: f32,
+    updates_this_frame: u32,
+    draw_calls: u32,
+    debug_overlay: bool,
+    audio: Audio,
+}
+
+impl CtxData {
+    /// Scale and offset that fit the logical screen into the window
+    /// without stretching it, leaving bars on the sides or at the top and
+    /// bottom when the aspect ratios differ.
+    fn viewport(&self) -> (f32, f32, f32) {
+        let (width, height) = self.physical_window_size;
+        let scale = (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT);
+        let x = (width - SCREEN_WIDTH * scale) / 2.0;
+        let y = (height - SCREEN_HEIGHT * scale) / 2.0;
+        (scale, x, y)
+    }
+
+    fn screen_coordinates(&self) -> graphics::Rect {
+        let (scale, x, y) = self.viewport();
+        graphics::Rect {
+            x: -x / scale,
+            y: -y / scale,
+            w: self.physical_window_size.0 / scale,
+            h: self.physical_window_size.1 / scale,
+        }
+    }
 }
 
 pub struct Ctx<'a> {
@@ -39,14 +172,66 @@ impl<'a> Ctx<'a> {
         self.data.current_mouse_press
     }
 
+    pub fn is_key_click(&self, key: KeyCode) -> bool {
+        !self.data.old_keys.contains(&key) && self.data.current_keys.contains(&key)
+    }
+
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.data.current_keys.contains(&key)
+    }
+
+    pub fn clicked_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.data.current_keys.difference(&self.data.old_keys).copied()
+    }
+
+    /// Text typed since the previous update, without control characters
+    /// like backspace or enter.
+    pub fn text_input(&self) -> &str {
+        &self.data.text_input
+    }
+
+    pub fn is_action_click(&self, action: Action) -> bool {
+        self.data.input.is_click(action)
+    }
+
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        self.data.input.is_pressed(action)
+    }
+
+    pub fn set_input_map(&mut self, map: InputMap) {
+        self.data.input.set_map(map);
+    }
+
+    pub fn is_mouse_moved(&self) -> bool {
+        self.data.old_mouse_position != self.data.current_mouse_position
+    }
+
     pub fn ggez(&mut self) -> &mut Context {
         self.ggez
     }
 
+    /// The chosen monitor and its index in the monitor list, falling back
+    /// to the one that the window is on.
+    fn selected_monitor(&self) -> (usize, winit::MonitorId) {
+        let window = ggez::graphics::window(self.ggez);
+        let chosen = self.data.monitor.as_ref().and_then(|name| window
+            .get_available_monitors()
+            .enumerate()
+            .find(|(index, m)| monitor_name(*index, m) == *name));
+        chosen.unwrap_or_else(|| {
+            let current = window.get_current_monitor();
+            let index = window
+                .get_available_monitors()
+                .position(|m| m.get_position() == current.get_position())
+                .unwrap_or(0);
+            (index, current)
+        })
+    }
+
     fn update_mode(&mut self) -> Result {
+        let (_, monitor) = self.selected_monitor();
         let window = ggez::graphics::window(self.ggez);
         let hidpi = window.get_hidpi_factor();
-        let monitor = window.get_current_monitor();
         let position = monitor.get_position();
         let monitor_size = monitor.get_dimensions();
         match self.data.mode {
@@ -63,8 +248,8 @@ impl<'a> Ctx<'a> {
                     .map(|s| s.to_physical(hidpi))
                     .unwrap_or(monitor_size);
                 let position = winit::dpi::PhysicalPosition {
-                    x: (monitor_size.width - outer_size.width) / 2.0,
-                    y: (monitor_size.height - outer_size.height) / 2.0,
+                    x: position.x + (monitor_size.width - outer_size.width) / 2.0,
+                    y: position.y + (monitor_size.height - outer_size.height) / 2.0,
                 }.to_logical(hidpi);
                 dbg!(monitor_size, outer_size, position);
                 window.set_position(position);
@@ -79,6 +264,7 @@ impl<'a> Ctx<'a> {
                 // window.set_always_on_top(true);
             }
         }
+        self.update_screen_coordinates()?;
         // let inner_size = window.get_inner_size().map(|s| (s.width as f32, s.height as f32)).unwrap_or(self.data.physical_window_size);
         // self.data.physical_window_size = inner_size;
         // println!("size: {:?}", inner_size);
@@ -103,6 +289,10 @@ impl<'a> Ctx<'a> {
         // Ok(())
     }
 
+    pub fn update_screen_coordinates(&mut self) -> Result {
+        graphics::set_screen_coordinates(self.ggez, self.data.screen_coordinates())
+    }
+
     pub fn set_window_size(&mut self, width: f32, height: f32) -> Result {
         self.data.window_size = (width, height);
         self.update_mode()
@@ -121,55 +311,129 @@ impl<'a> Ctx<'a> {
         self.data.mode
     }
 
+    pub fn monitors(&self) -> Vec<Monitor> {
+        ggez::graphics::window(self.ggez)
+            .get_available_monitors()
+            .enumerate()
+            .map(|(index, m)| Monitor::from_id(index, &m))
+            .collect()
+    }
+
+    pub fn set_monitor(&mut self, monitor: Option<String>) -> Result {
+        self.data.monitor = monitor;
+        self.update_mode()
+    }
+
+    pub fn monitor(&self) -> Monitor {
+        let (index, monitor) = self.selected_monitor();
+        Monitor::from_id(index, &monitor)
+    }
+
     pub fn mouse_position(&self) -> (f32, f32) {
         let mouse = ggez::input::mouse::position(self.ggez);
-        let x = mouse.x / self.data.physical_window_size.0 * SCREEN_WIDTH;
-        let y = mouse.y / self.data.physical_window_size.1 * SCREEN_HEIGHT;
+        let (scale, offset_x, offset_y) = self.data.viewport();
+        let x = (mouse.x - offset_x) / scale;
+        let y = (mouse.y - offset_y) / scale;
         (x, y)
     }
 
     pub fn round_to_screen(&self, point: (f32, f32)) -> (f32, f32) {
-        let scale_w = self.data.window_size.0 / SCREEN_WIDTH;
-        let scale_h = self.data.window_size.1 / SCREEN_HEIGHT;
-        let x = (point.0 * scale_w).round() / scale_w;
-        let y = (point.1 * scale_h).round() / scale_h;
+        let (scale, _, _) = self.data.viewport();
+        let x = (point.0 * scale).round() / scale;
+        let y = (point.1 * scale).round() / scale;
         (x, y)
     }
 
     pub fn scroll_position(&self) -> (f32, f32) {
         self.data.scroll_position
     }
+
+    /// How far drawing is between the last update and the next one, from
+    /// 0 to 1. Useful for drawing moving things smoothly when the frame
+    /// rate is higher than the update rate.
+    pub fn interpolation(&self) -> f32 {
+        self.data.interpolation
+    }
+
+    pub fn audio(&mut self) -> AudioCtx<'_> {
+        AudioCtx {
+            ggez: self.ggez,
+            audio: &mut self.data.audio,
+        }
+    }
+
+    pub fn set_debug_overlay(&mut self, enabled: bool) {
+        self.data.debug_overlay = enabled;
+    }
+
+    pub fn debug_overlay(&self) -> bool {
+        self.data.debug_overlay
+    }
 }
 
 pub trait Game {
-    fn update(&mut self, ctx: &mut Ctx<'_>) -> Result;
+    fn update(&mut self, ctx: &mut Ctx<'_>, dt: f32) -> Result;
     fn draw(&mut self, ctx: &mut Ctx<'_>) -> Result;
 }
 
 struct GameRunner {
     game: Box<dyn Game>,
-    position_set: bool,
+    mode_set: bool,
     ctx_data: CtxData,
+    capture: Capture,
 }
 
 const DEFAULT_PHYSICAL_WIDTH: f32 = 1600.0;
 const DEFAULT_PHYSICAL_HEIGHT: f32 = 900.0;
 
 impl GameRunner {
-    fn new(game: Box<dyn Game>) -> Self {
+    fn new(game: Box<dyn Game>, window: WindowSettings, audio: Audio) -> Self {
         GameRunner {
             game,
-            position_set: false,
+            mode_set: false,
             ctx_data: CtxData {
-                window_size: (DEFAULT_PHYSICAL_WIDTH, DEFAULT_PHYSICAL_HEIGHT),
-                physical_window_size: (DEFAULT_PHYSICAL_WIDTH, DEFAULT_PHYSICAL_HEIGHT),
-                mode: WindowMode::Windowed,
+                window_size: (window.width, window.height),
+                physical_window_size: (window.width, window.height),
+                mode: window.mode,
+                monitor: window.monitor,
                 old_mouse_press: false,
                 current_mouse_press: false,
+                old_mouse_position: (0.0, 0.0),
+                current_mouse_position: (0.0, 0.0),
+                old_keys: HashSet::new(),
+                current_keys: HashSet::new(),
+                pending_text: String::new(),
+                text_input: String::new(),
+                input: InputState::default(),
                 scroll_position: (0.0, 0.0),
+                interpolation: 0.0,
+                updates_this_frame: 0,
+                draw_calls: 0,
+                debug_overlay: false,
+                audio,
             },
+            capture: Capture::default(),
         }
     }
+
+    fn update_input(&mut self, ctx: &mut Context) {
+        self.ctx_data.old_mouse_press = self.ctx_data.current_mouse_press;
+        self.ctx_data.current_mouse_press = ggez::input::mouse::button_pressed(
+            ctx,
+            ggez::input::mouse::MouseButton::Left,
+        );
+        let mouse = ggez::input::mouse::position(ctx);
+        self.ctx_data.old_mouse_position = std::mem::replace(
+            &mut self.ctx_data.current_mouse_position,
+            (mouse.x, mouse.y),
+        );
+        self.ctx_data.old_keys = std::mem::replace(
+            &mut self.ctx_data.current_keys,
+            ggez::input::keyboard::pressed_keys(ctx).clone(),
+        );
+        self.ctx_data.input.update(&self.ctx_data.current_keys);
+        self.ctx_data.text_input = std::mem::take(&mut self.ctx_data.pending_text);
+    }
 }
 
 impl event::EventHandler for GameRunner {
@@ -178,55 +442,147 @@ impl event::EventHandler for GameRunner {
         self.ctx_data.scroll_position.1 -= y;
     }
 
+    // The default handler quits on escape, but games use it for menus.
+    fn key_down_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymods: event::KeyMods, repeat: bool) {
+        if !repeat {
+            self.capture.key_down(keycode, keymods);
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if !character.is_control() {
+            self.ctx_data.pending_text.push(character);
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, _ctx: &mut Context, button: event::Button, _id: event::GamepadId) {
+        self.ctx_data.input.button_down(button);
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, button: event::Button, _id: event::GamepadId) {
+        self.ctx_data.input.button_up(button);
+    }
+
+    fn gamepad_axis_event(&mut self, _ctx: &mut Context, axis: event::Axis, value: f32, _id: event::GamepadId) {
+        self.ctx_data.input.axis(axis, value);
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        self.ctx_data.physical_window_size = (width, height);
+        let result = Ctx { ggez: ctx, data: &mut self.ctx_data }.update_screen_coordinates();
+        if let Err(e) = result {
+            eprintln!("failed to update screen coordinates: {}", e);
+        }
+    }
+
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if !self.position_set {
-            self.position_set = true;
-            let window = ggez::graphics::window(ctx);
-            window.set_position(winit::dpi::LogicalPosition {
-                x: 100.0,
-                y: 50.0,
-            });
+        if !self.mode_set {
+            self.mode_set = true;
+            Ctx { ggez: ctx, data: &mut self.ctx_data }.update_mode()?;
         }
-        if timer::ticks(ctx) % 180 == 0 {
-            println!(
-                "delta frame time: {:?}, average fps: {}",
-                timer::delta(ctx),
-                timer::fps(ctx),
-            );
+        let mut updates = 0;
+        while timer::check_update_time(ctx, UPDATES_PER_SECOND) {
+            if updates == MAX_UPDATES_PER_FRAME {
+                continue;
+            }
+            updates += 1;
+            // Input is sampled once per update, so that each click is seen
+            // by exactly one update no matter how many run in a frame.
+            self.update_input(ctx);
+            self.game.update(&mut Ctx { ggez: ctx, data: &mut self.ctx_data }, UPDATE_DT)?;
+            self.ctx_data.audio.update(UPDATE_DT);
         }
-        self.ctx_data.old_mouse_press = self.ctx_data.current_mouse_press;
-        self.ctx_data.current_mouse_press = ggez::input::mouse::button_pressed(
-            ctx,
-            ggez::input::mouse::MouseButton::Left,
-        );
-        self.game.update(&mut Ctx { ggez: ctx, data: &mut self.ctx_data })
+        self.ctx_data.updates_this_frame = updates;
+        Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, graphics::WHITE);
+        let remaining = timer::duration_to_f64(timer::remaining_update_time(ctx)) as f32;
+        self.ctx_data.interpolation = (remaining / UPDATE_DT).min(1.0);
+        graphics::clear(ctx, BACKGROUND);
+        self.ctx_data.draw_calls = 0;
         self.game.draw(&mut Ctx { ggez: ctx, data: &mut self.ctx_data })?;
+        if self.ctx_data.debug_overlay {
+            draw_debug_overlay(ctx, &self.ctx_data)?;
+        }
+        draw_bars(ctx, &self.ctx_data)?;
+        self.capture.frame(ctx, timer::duration_to_f64(timer::delta(ctx)) as f32);
         graphics::present(ctx)?;
         Ok(())
     }
 }
 
-pub fn run(create_game: &dyn Fn(&mut Context) -> Result<Box<dyn Game>>) -> Result {
-    let resource_dir = std::path::PathBuf::from("./resources");
+fn draw_debug_overlay(ctx: &mut Context, data: &CtxData) -> Result {
+    let text = format!(
+        "FPS: {:.1}\nFrame time: {:.1} ms\nUpdates: {}\nDraw calls: {}",
+        timer::fps(ctx),
+        timer::duration_to_f64(timer::delta(ctx)) * 1000.0,
+        data.updates_this_frame,
+        data.draw_calls,
+    );
+    let (scale, _, _) = data.viewport();
+    let fragment = graphics::TextFragment::new(text).scale(graphics::Scale::uniform(24.0 * scale));
+    let text = graphics::Text::new(fragment);
+    let (width, height) = text.dimensions(ctx);
+    let (width, height) = (width as f32 / scale, height as f32 / scale);
+    let (x, y) = (10.0, SCREEN_HEIGHT - height - 20.0);
+    let background = graphics::Rect::new(x, y, width + 20.0, height + 10.0);
+    let color = graphics::Color::new(0.0, 0.0, 0.0, 0.7);
+    let mesh = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), background, color)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    let draw = graphics::DrawParam::new()
+        .dest([x + 10.0, y + 5.0])
+        .scale([1.0 / scale, 1.0 / scale])
+        .color(graphics::WHITE);
+    graphics::draw(ctx, &text, draw)
+}
+
+fn draw_bars(ctx: &mut Context, data: &CtxData) -> Result {
+    let screen = data.screen_coordinates();
+    let bars = [
+        graphics::Rect::new(screen.x, screen.y, -screen.x, screen.h),
+        graphics::Rect::new(SCREEN_WIDTH, screen.y, -screen.x, screen.h),
+        graphics::Rect::new(screen.x, screen.y, screen.w, -screen.y),
+        graphics::Rect::new(screen.x, SCREEN_HEIGHT, screen.w, -screen.y),
+    ];
+    for bar in bars.iter().filter(|bar| bar.w > 0.0 && bar.h > 0.0) {
+        let mesh = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), *bar, graphics::BLACK)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
+
+pub(crate) const RESOURCE_DIR: &str = "./resources";
 
-    let (ctx, event_loop) = &mut ggez::ContextBuilder::new("ccg", "domantas")
+fn context_builder() -> ggez::ContextBuilder {
+    let resource_dir = std::path::PathBuf::from(RESOURCE_DIR);
+    ggez::ContextBuilder::new(GAME_ID, AUTHOR)
         .add_resource_path(resource_dir)
         .window_setup(ggez::conf::WindowSetup::default().title("Some sort of ccg"))
+}
+
+pub fn run(window: WindowSettings, create_game: &dyn Fn(&mut Context) -> Result<Box<dyn Game>>) -> Result {
+    let (ctx, event_loop) = &mut context_builder()
         .window_mode(ggez::conf::WindowMode::default()
-            .dimensions(DEFAULT_PHYSICAL_WIDTH, DEFAULT_PHYSICAL_HEIGHT))
+            .dimensions(window.width, window.height))
         .build()?;
-        
+
     let game = create_game(ctx)?;
-    ggez::graphics::set_screen_coordinates(ctx, ggez::graphics::Rect {
-        x: 0.0,
-        y: 0.0,
-        w: SCREEN_WIDTH,
-        h: SCREEN_HEIGHT,
-    })?;
-    let runner = &mut GameRunner::new(game);
+    let audio = Audio::load(ctx).unwrap_or_else(|e| {
+        eprintln!("failed to load sounds: {}", e);
+        Audio::empty()
+    });
+    let runner = &mut GameRunner::new(game, window, audio);
+    Ctx { ggez: ctx, data: &mut runner.ctx_data }.update_screen_coordinates()?;
     event::run(ctx, event_loop, runner)
 }
+
+/// Creates a context without running the game, for tools that only need to
+/// load resources and render. A small window is still shown while `tool`
+/// runs, as the context cannot be created without one.
+pub fn run_tool(tool: &dyn Fn(&mut Context) -> Result) -> Result {
+    let (ctx, _) = &mut context_builder()
+        .window_mode(ggez::conf::WindowMode::default().dimensions(320.0, 180.0))
+        .build()?;
+    tool(ctx)
+}
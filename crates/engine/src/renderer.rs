@@ -1,29 +1,74 @@
 use ggez::Context;
-use ggez::graphics::{DrawParam, Image, Rect, Color};
+use ggez::conf::NumSamples;
+use ggez::graphics::{Canvas, DrawParam, FilterMode, Image, Rect, Color, spritebatch::SpriteBatch};
 use ggez::nalgebra::Point2;
 use crate::{Ctx, CtxData, Result};
+use crate::atlas::{Texture, TextureAtlas};
+use crate::camera::Camera;
+use crate::effects::Effects;
+use crate::text::{piece_color, Fonts, PieceKind, ScreenText};
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Atlas {
+    Icons,
+    Page(u32),
+}
 
 pub struct Renderer {
     icons: Image,
-    textures: Vec<Image>,
+    atlas: TextureAtlas,
+    resolution: f32,
     indices: Textures,
+    fonts: Fonts,
+    // Sprites are collected here while they use the same image, and drawn
+    // together when something else needs to be drawn.
+    batch: SpriteBatch,
+    batch_atlas: Option<Atlas>,
+    batch_len: usize,
+    // Kept between frames for `FrameRenderer::draw_layer`.
+    layer: Option<Canvas>,
 }
 
 impl Renderer {
-    pub fn new(icons: Image, textures: Vec<Image>, indices: Textures) -> Self {
-        Renderer { icons, textures, indices }
+    pub fn new(icons: Image, atlas: TextureAtlas, indices: Textures, fonts: Fonts) -> Self {
+        Renderer {
+            batch: SpriteBatch::new(icons.clone()),
+            batch_atlas: None,
+            batch_len: 0,
+            layer: None,
+            icons,
+            atlas,
+            resolution: 1.0,
+            indices,
+            fonts,
+        }
     }
 
-    pub fn frame<'a>(&'a mut self, ctx: &'a mut Ctx<'_>) -> FrameRenderer<'a> {
-        FrameRenderer {
+    /// Renders textures again if the window was resized to a different
+    /// resolution. Rendering presents a frame, so this belongs in
+    /// `Game::update` rather than in the middle of drawing one.
+    pub fn update(&mut self, ctx: &mut Ctx<'_>) -> Result {
+        let (resolution, _, _) = ctx.data.viewport();
+        if (resolution - self.resolution).abs() > 0.01 {
+            self.atlas.render(ctx.ggez, resolution)?;
+            self.resolution = resolution;
+            // Rendering the atlas moves the screen coordinates around.
+            ctx.update_screen_coordinates()?;
+        }
+        Ok(())
+    }
+
+    pub fn frame<'a>(&'a mut self, ctx: &'a mut Ctx<'_>) -> Result<FrameRenderer<'a>> {
+        Ok(FrameRenderer {
             renderer: self,
             ctx: ctx.ggez,
             ctx_data: ctx.data,
-        }
+            transforms: 0,
+        })
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Icon {
     index: u32,
 }
@@ -59,8 +104,46 @@ impl Icon {
     pub const fn new(index: u32) -> Icon {
         Icon { index }
     }
+
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Looks up an icon by the name used in data files and text markup.
+    pub fn from_name(name: &str) -> Option<Icon> {
+        ICON_NAMES.iter().find(|(n, _)| *n == name).map(|&(_, icon)| icon)
+    }
 }
 
+const ICON_NAMES: &[(&str, Icon)] = &[
+    ("circle", Icon::CIRCLE),
+    ("dot", Icon::DOT),
+    ("square", Icon::SQUARE),
+    ("sword", Icon::SWORD),
+    ("heart", Icon::HEART),
+    ("shield", Icon::SHIELD),
+    ("fighter", Icon::FIGHTER),
+    ("beholder", Icon::BEHOLDER),
+    ("card", Icon::CARD),
+    ("play", Icon::PLAY),
+    ("card-back", Icon::CARD_BACK),
+    ("coin", Icon::COIN),
+    ("cross", Icon::CROSS),
+    ("bang", Icon::BANG),
+    ("red-circle", Icon::RED_CIRCLE),
+    ("blue-beholder", Icon::BLUE_BEHOLDER),
+    ("green-heart", Icon::GREEN_HEART),
+    ("broken", Icon::BROKEN),
+    ("deck", Icon::DECK),
+    ("trap-deck", Icon::TRAP_DECK),
+    ("black", Icon::BLACK),
+    ("disarm", Icon::DISARM),
+    ("red-sword", Icon::RED_SWORD),
+    ("bow", Icon::BOW),
+    ("fighter-2", Icon::FIGHTER_2),
+    ("chicken", Icon::CHICKEN),
+];
+
 pub struct Textures {
     pub button: Texture,
     pub button_hover: Texture,
@@ -68,21 +151,12 @@ pub struct Textures {
     pub card_back: Texture,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Texture {
-    index: u32,
-}
-
-impl Texture {
-    pub const fn new(index: u32) -> Texture {
-        Texture { index }
-    }
-}
-
 pub struct FrameRenderer<'a> {
     renderer: &'a mut Renderer,
     ctx: &'a mut Context,
     ctx_data: &'a mut CtxData,
+    // Number of transforms pushed onto the ggez stack by this frame.
+    transforms: usize,
 }
 
 impl<'a> FrameRenderer<'a> {
@@ -90,7 +164,16 @@ impl<'a> FrameRenderer<'a> {
         &self.renderer.indices
     }
 
+    pub fn fonts(&self) -> &Fonts {
+        &self.renderer.fonts
+    }
+
+    /// For drawing directly with ggez. Sprites batched so far are drawn
+    /// first, so that they end up below whatever is drawn next.
     pub fn ggez(&mut self) -> &mut Context {
+        if let Err(e) = self.flush() {
+            eprintln!("failed to draw sprites: {}", e);
+        }
         self.ctx
     }
 
@@ -101,48 +184,197 @@ impl<'a> FrameRenderer<'a> {
         }
     }
 
-    pub fn draw(&mut self, texture: Texture, x: f32, y: f32, width: f32, height: f32) -> Result {
-        let texture = &self.renderer.textures[texture.index as usize];
-        ggez::graphics::draw(
-            self.ctx,
-            texture,
-            DrawParam::new()
-                .dest(Point2::new(x, y))
-                .scale([
-                    width / f32::from(texture.width()),
-                    height / f32::from(texture.height()),
-                ])
-                .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 }),
-        )
+    /// Transforms everything drawn until the matching `pop_transform`, on
+    /// top of the transforms that are already pushed.
+    pub fn push_transform(&mut self, transform: DrawParam) -> Result {
+        self.flush()?;
+        ggez::graphics::push_transform(self.ctx, None::<ggez::mint::ColumnMatrix4<f32>>);
+        ggez::graphics::mul_transform(self.ctx, transform.to_matrix());
+        ggez::graphics::apply_transformations(self.ctx)?;
+        self.transforms += 1;
+        Ok(())
     }
 
-    pub fn draw_icon(&mut self, icon: Icon, x: f32, y: f32, width: f32, height: f32) -> Result {
-        let row = (icon.index / 8) as f32;
-        let col = (icon.index % 8) as f32;
+    /// Draws everything until the matching `pop_transform` in the world
+    /// coordinates of `camera`.
+    pub fn push_camera(&mut self, camera: &Camera) -> Result {
+        let interpolation = self.ctx_data.interpolation;
+        self.push_transform(camera.transform(interpolation))
+    }
+
+    pub fn pop_transform(&mut self) -> Result {
+        assert!(self.transforms > 0, "no transform to pop");
+        self.flush()?;
+        ggez::graphics::pop_transform(self.ctx);
+        ggez::graphics::apply_transformations(self.ctx)?;
+        self.transforms -= 1;
+        Ok(())
+    }
+
+    /// Adds a sprite to the batch, first drawing the batch if it was
+    /// collecting sprites from a different image.
+    fn batch(&mut self, atlas: Atlas, draw: DrawParam) -> Result {
+        if self.renderer.batch_atlas != Some(atlas) {
+            self.flush()?;
+            let image = match atlas {
+                Atlas::Icons => &self.renderer.icons,
+                Atlas::Page(index) => self.renderer.atlas.page(index),
+            };
+            self.renderer.batch.set_image(image.clone());
+            self.renderer.batch_atlas = Some(atlas);
+        }
+        self.renderer.batch.add(draw);
+        self.renderer.batch_len += 1;
+        Ok(())
+    }
+
+    /// Draws all sprites batched so far.
+    pub fn flush(&mut self) -> Result {
+        self.renderer.batch_atlas = None;
+        if self.renderer.batch_len == 0 {
+            return Ok(());
+        }
+        ggez::graphics::draw(self.ctx, &self.renderer.batch, DrawParam::default())?;
+        self.renderer.batch.clear();
+        self.renderer.batch_len = 0;
+        self.ctx_data.draw_calls += 1;
+        Ok(())
+    }
+
+    pub fn draw(&mut self, texture: Texture, x: f32, y: f32, width: f32, height: f32) -> Result {
+        let index = self.renderer.atlas.page_index(texture);
+        let page = self.renderer.atlas.page(index);
+        let src = self.renderer.atlas.uv(texture);
         let draw = DrawParam::new()
             .dest(Point2::new(x, y))
-            .scale([width / 16.0, height / 16.0])
-            .src(Rect {
-                x: col / 8.0,
-                y: row / 8.0,
-                w: 0.125,
-                h: 0.125,
-            });
-        ggez::graphics::draw(self.ctx, &self.renderer.icons, draw)
+            .scale([
+                width / (src.w * f32::from(page.width())),
+                height / (src.h * f32::from(page.height())),
+            ])
+            .src(src);
+        self.batch(Atlas::Page(index), draw)
     }
 
-    pub fn draw_fade(&mut self, opacity: f32) -> Result {
-        let mut opacity = (opacity * 256.0) as i64;
-        if opacity < 0 {
-            opacity = 0;
+    pub fn draw_icon(&mut self, icon: Icon, x: f32, y: f32, width: f32, height: f32) -> Result {
+        self.batch(Atlas::Icons, icon_param(icon, x, y, width, height))
+    }
+
+    /// Scale from logical screen units to physical pixels.
+    fn resolution(&self) -> f32 {
+        self.ctx_data.viewport().0
+    }
+
+    pub fn draw_text(&mut self, text: &mut ScreenText, x: f32, y: f32, color: Color) -> Result {
+        self.text(text, x, y, color, false)
+    }
+
+    /// Queues text to be drawn with the next `draw_queued_text`, which is
+    /// faster than drawing lots of text one by one. Icons in the text are
+    /// batched like other icons.
+    pub fn queue_text(&mut self, text: &mut ScreenText, x: f32, y: f32, color: Color) -> Result {
+        self.text(text, x, y, color, true)
+    }
+
+    fn text(&mut self, text: &mut ScreenText, x: f32, y: f32, color: Color, queue: bool) -> Result {
+        let resolution = self.resolution();
+        let (x, y) = self.ctx().round_to_screen((x, y));
+        let size = text.size();
+        let layout = text.layout(self.ctx, &self.renderer.fonts, resolution);
+        for piece in &layout.pieces {
+            let (x, y) = (x + piece.x, y + piece.y);
+            match &piece.kind {
+                PieceKind::Text(text, own_color) => {
+                    let color = piece_color(*own_color, color);
+                    if queue {
+                        let dest = Point2::new(x * resolution, y * resolution);
+                        ggez::graphics::queue_text(self.ctx, text, dest, Some(color));
+                    } else {
+                        self.flush()?;
+                        let draw = DrawParam::new()
+                            .dest(Point2::new(x, y))
+                            .scale([1.0 / resolution, 1.0 / resolution])
+                            .color(color);
+                        ggez::graphics::draw(self.ctx, text, draw)?;
+                        self.ctx_data.draw_calls += 1;
+                    }
+                }
+                PieceKind::Icon(icon) => {
+                    let draw = icon_param(*icon, x, y, size, size)
+                        .color(Color::new(1.0, 1.0, 1.0, color.a));
+                    self.batch(Atlas::Icons, draw)?;
+                }
+            }
         }
-        if opacity > 255 {
-            opacity = 255;
+        Ok(())
+    }
+
+    pub fn draw_queued_text(&mut self) -> Result {
+        self.flush()?;
+        let resolution = self.resolution();
+        let draw = DrawParam::new().scale([1.0 / resolution, 1.0 / resolution]);
+        ggez::graphics::draw_queued_text(self.ctx, draw, None, FilterMode::Linear)?;
+        self.ctx_data.draw_calls += 1;
+        Ok(())
+    }
+
+    /// Width and height of the text in logical screen units.
+    pub fn text_dimensions(&mut self, text: &mut ScreenText) -> (f32, f32) {
+        let resolution = self.resolution();
+        text.dimensions(self.ctx, &self.renderer.fonts, resolution)
+    }
+
+    pub fn draw_effects(&mut self, effects: &mut Effects) -> Result {
+        for (icon, x, y, size, opacity) in effects.particles() {
+            let draw = icon_param(icon, x, y, size, size)
+                .color(Color::new(1.0, 1.0, 1.0, opacity));
+            self.batch(Atlas::Icons, draw)?;
         }
-        let opacity = opacity as u8;
+        for (text, opacity) in effects.texts() {
+            let (width, height) = self.text_dimensions(&mut text.text);
+            let x = text.position.0 - width / 2.0;
+            let y = text.position.1 - height / 2.0;
+            let color = Color { a: opacity, ..text.color };
+            self.draw_text(&mut text.text, x, y, color)?;
+        }
+        Ok(())
+    }
+
+    /// Draws everything `draw` draws into a separate layer, and then draws
+    /// the layer over the screen with the given opacity. Layers can't be
+    /// nested, and should be drawn without any transforms pushed.
+    pub fn draw_layer(&mut self, opacity: f32, draw: impl FnOnce(&mut Self) -> Result) -> Result {
+        self.flush()?;
+        let (width, height) = self.ctx_data.physical_window_size;
+        let (width, height) = (width.round() as u16, height.round() as u16);
+        let layer = match self.renderer.layer.take() {
+            Some(layer) if (layer.image().width(), layer.image().height()) == (width, height) => layer,
+            _ => Canvas::new(self.ctx, width, height, NumSamples::One)?,
+        };
+        ggez::graphics::set_canvas(self.ctx, Some(&layer));
+        ggez::graphics::clear(self.ctx, crate::BACKGROUND);
+        let result = draw(self).and_then(|()| self.flush());
+        ggez::graphics::set_canvas(self.ctx, None);
+        result?;
+        let screen = self.ctx_data.screen_coordinates();
+        let param = DrawParam::new()
+            .dest(Point2::new(screen.x, screen.y))
+            .scale([screen.w / f32::from(width), screen.h / f32::from(height)])
+            .color(Color::new(1.0, 1.0, 1.0, opacity));
+        ggez::graphics::draw(self.ctx, &layer, param)?;
+        self.ctx_data.draw_calls += 1;
+        self.renderer.layer = Some(layer);
+        Ok(())
+    }
+
+    pub fn draw_fade(&mut self, opacity: f32) -> Result {
+        self.draw_shade(0.0, 0.0, crate::SCREEN_WIDTH, crate::SCREEN_HEIGHT, opacity)
+    }
+
+    pub fn draw_shade(&mut self, x: f32, y: f32, width: f32, height: f32, opacity: f32) -> Result {
+        let opacity = ((opacity * 256.0) as i64).clamp(0, 255) as u8;
         let draw = DrawParam::new()
-            .dest(Point2::new(0.0, 0.0))
-            .scale([crate::SCREEN_WIDTH / 16.0, crate::SCREEN_HEIGHT / 16.0])
+            .dest(Point2::new(x, y))
+            .scale([width / 16.0, height / 16.0])
             .src(Rect {
                 x: 5.0 / 8.0,
                 y: 2.0 / 8.0,
@@ -150,6 +382,35 @@ impl<'a> FrameRenderer<'a> {
                 h: 0.125,
             })
             .color(Color::from_rgba(opacity, opacity, opacity, opacity));
-        ggez::graphics::draw(self.ctx, &self.renderer.icons, draw)
+        self.batch(Atlas::Icons, draw)
+    }
+}
+
+impl Drop for FrameRenderer<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            eprintln!("failed to draw sprites: {}", e);
+        }
+        // Transforms left on the stack would affect the next frame.
+        while self.transforms > 0 {
+            if let Err(e) = self.pop_transform() {
+                eprintln!("failed to reset transform: {}", e);
+                break;
+            }
+        }
     }
 }
+
+pub(crate) fn icon_param(icon: Icon, x: f32, y: f32, width: f32, height: f32) -> DrawParam {
+    let row = (icon.index / 8) as f32;
+    let col = (icon.index % 8) as f32;
+    DrawParam::new()
+        .dest(Point2::new(x, y))
+        .scale([width / 16.0, height / 16.0])
+        .src(Rect {
+            x: col / 8.0,
+            y: row / 8.0,
+            w: 0.125,
+            h: 0.125,
+        })
+}
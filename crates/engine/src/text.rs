@@ -0,0 +1,482 @@
+use ggez::Context;
+use ggez::graphics::{self, Align, Color, DrawParam, Font, Image, Scale, Text, TextFragment};
+use ggez::nalgebra::Point2;
+use crate::{Icon, Result};
+use crate::renderer::icon_param;
+
+const REGULAR_FONT: &str = "/fonts/DejaVuSans.ttf";
+const BOLD_FONT: &str = "/fonts/DejaVuSans-Bold.ttf";
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Fonts {
+    pub regular: Font,
+    pub bold: Font,
+}
+
+impl Fonts {
+    /// The font files, for telling when text drawn with them may look
+    /// different.
+    pub const FILES: &'static [&'static str] = &[REGULAR_FONT, BOLD_FONT];
+
+    /// Loads the fonts from `resources/fonts`. Fonts that fail to load are
+    /// replaced with the built in one, so text is never missing.
+    pub fn load(ctx: &mut Context) -> Fonts {
+        let mut load = |path: &str| Font::new(ctx, path).unwrap_or_else(|e| {
+            eprintln!("failed to load font {}: {}", path, e);
+            Font::default()
+        });
+        Fonts {
+            regular: load(REGULAR_FONT),
+            bold: load(BOLD_FONT),
+        }
+    }
+
+    fn get(&self, style: &Style) -> Font {
+        if style.bold { self.bold } else { self.regular }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    color: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Span {
+    Text(String, Style),
+    Icon(Icon),
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum PieceKind {
+    Text(Text, Option<Color>),
+    Icon(Icon),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Piece {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) kind: PieceKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    resolution: f32,
+    pub(crate) pieces: Vec<Piece>,
+    width: f32,
+    height: f32,
+}
+
+/// Text sized in logical screen units, but rasterised at the resolution
+/// it is drawn at so that it stays sharp whatever the window size is.
+/// Draw it with `FrameRenderer::draw_text` or `FrameRenderer::queue_text`,
+/// or with `draw_with` when not drawing to the screen.
+///
+/// Text made with `ScreenText::rich` can use markup: `[b]bold[/b]`,
+/// `[color=red]coloured[/color]`, icons like `[sword]`, and `[[` for a
+/// plain `[`.
+#[derive(Debug, Clone)]
+pub struct ScreenText {
+    contents: String,
+    markup: bool,
+    size: f32,
+    bounds: Option<(f32, Align)>,
+    spans: Vec<Span>,
+    layout: Option<Layout>,
+}
+
+impl ScreenText {
+    pub fn new(contents: impl Into<String>, size: f32) -> ScreenText {
+        ScreenText::build(contents.into(), false, size)
+    }
+
+    pub fn rich(markup: impl Into<String>, size: f32) -> ScreenText {
+        ScreenText::build(markup.into(), true, size)
+    }
+
+    fn build(contents: String, markup: bool, size: f32) -> ScreenText {
+        let spans = parse(&contents, markup);
+        ScreenText {
+            contents,
+            markup,
+            size,
+            bounds: None,
+            spans,
+            layout: None,
+        }
+    }
+
+    /// Wraps the text to the given width.
+    pub fn with_bounds(mut self, width: f32, align: Align) -> ScreenText {
+        self.bounds = Some((width, align));
+        self.layout = None;
+        self
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn set_contents(&mut self, contents: &str) {
+        if self.contents != contents {
+            self.contents = contents.to_owned();
+            self.spans = parse(contents, self.markup);
+            self.layout = None;
+        }
+    }
+
+    /// Width and height in logical screen units.
+    pub fn dimensions(&mut self, ctx: &mut Context, fonts: &Fonts, resolution: f32) -> (f32, f32) {
+        let layout = self.layout(ctx, fonts, resolution);
+        (layout.width, layout.height)
+    }
+
+    /// Draws the text with its top left corner at `position`. Pieces of
+    /// text without a colour of their own use `color`.
+    pub fn draw_with(
+        &mut self,
+        ctx: &mut Context,
+        fonts: &Fonts,
+        icons: &Image,
+        position: (f32, f32),
+        resolution: f32,
+        color: Color,
+    ) -> Result {
+        let size = self.size;
+        let layout = self.layout(ctx, fonts, resolution);
+        for piece in &layout.pieces {
+            let (x, y) = (position.0 + piece.x, position.1 + piece.y);
+            match &piece.kind {
+                PieceKind::Text(text, own_color) => {
+                    let draw = DrawParam::new()
+                        .dest(Point2::new(x, y))
+                        .scale([1.0 / resolution, 1.0 / resolution])
+                        .color(piece_color(*own_color, color));
+                    graphics::draw(ctx, text, draw)?;
+                }
+                PieceKind::Icon(icon) => {
+                    let draw = icon_param(*icon, x, y, size, size)
+                        .color(Color::new(1.0, 1.0, 1.0, color.a));
+                    graphics::draw(ctx, icons, draw)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub(crate) fn layout(&mut self, ctx: &mut Context, fonts: &Fonts, resolution: f32) -> &Layout {
+        let outdated = self.layout.as_ref().is_none_or(|l| l.resolution != resolution);
+        if outdated {
+            self.layout = Some(LayoutBuilder::new(self, ctx, fonts, resolution).build(&self.spans));
+        }
+        self.layout.as_ref().unwrap()
+    }
+}
+
+pub(crate) fn piece_color(own: Option<Color>, default: Color) -> Color {
+    match own {
+        Some(color) => Color { a: default.a, ..color },
+        None => default,
+    }
+}
+
+struct Run {
+    text: String,
+    style: Style,
+    x: f32,
+}
+
+struct LayoutBuilder<'a> {
+    ctx: &'a mut Context,
+    fonts: &'a Fonts,
+    resolution: f32,
+    scale: Scale,
+    size: f32,
+    bounds: Option<(f32, Align)>,
+    line_height: f32,
+    space: f32,
+    pieces: Vec<Piece>,
+    line: Vec<Piece>,
+    line_count: usize,
+    line_width: f32,
+    width: f32,
+    run: Option<Run>,
+    x: f32,
+}
+
+impl<'a> LayoutBuilder<'a> {
+    fn new(text: &ScreenText, ctx: &'a mut Context, fonts: &'a Fonts, resolution: f32) -> LayoutBuilder<'a> {
+        let scale = Scale::uniform(text.size * resolution);
+        let mut builder = LayoutBuilder {
+            ctx,
+            fonts,
+            resolution,
+            scale,
+            size: text.size,
+            bounds: text.bounds,
+            line_height: 0.0,
+            space: 0.0,
+            pieces: Vec::new(),
+            line: Vec::new(),
+            line_count: 0,
+            line_width: 0.0,
+            width: 0.0,
+            run: None,
+            x: 0.0,
+        };
+        let regular = Style::default();
+        builder.line_height = builder.raster("Ag", &regular).height(builder.ctx) as f32 / resolution;
+        builder.space = builder.measure("a a", &regular) - builder.measure("aa", &regular);
+        builder
+    }
+
+    fn raster(&self, text: &str, style: &Style) -> Text {
+        Text::new(TextFragment::new(text).font(self.fonts.get(style)).scale(self.scale))
+    }
+
+    fn measure(&mut self, text: &str, style: &Style) -> f32 {
+        self.raster(text, style).width(self.ctx) as f32 / self.resolution
+    }
+
+    fn build(mut self, spans: &[Span]) -> Layout {
+        for span in spans {
+            match span {
+                Span::Text(text, style) => {
+                    for (i, word) in text.split(' ').enumerate() {
+                        if i > 0 {
+                            self.append(" ", style);
+                            self.x += self.space;
+                        }
+                        if !word.is_empty() {
+                            let width = self.measure(word, style);
+                            self.wrap(width);
+                            self.append(word, style);
+                            self.x += width;
+                            self.line_width = self.x;
+                        }
+                    }
+                }
+                Span::Icon(icon) => {
+                    self.wrap(self.size);
+                    self.flush();
+                    self.line.push(Piece {
+                        x: self.x,
+                        y: (self.line_height - self.size) / 2.0,
+                        kind: PieceKind::Icon(*icon),
+                    });
+                    self.x += self.size;
+                    self.line_width = self.x;
+                }
+                Span::Newline => self.finish_line(),
+            }
+        }
+        self.finish_line();
+        Layout {
+            resolution: self.resolution,
+            pieces: self.pieces,
+            width: self.width,
+            height: self.line_count as f32 * self.line_height,
+        }
+    }
+
+    fn wrap(&mut self, width: f32) {
+        if let Some((max_width, _)) = self.bounds {
+            if self.x > 0.0 && self.x + width > max_width {
+                self.finish_line();
+            }
+        }
+    }
+
+    fn append(&mut self, text: &str, style: &Style) {
+        match &mut self.run {
+            Some(run) if run.style == *style => run.text.push_str(text),
+            _ => {
+                self.flush();
+                self.run = Some(Run { text: text.to_owned(), style: style.clone(), x: self.x });
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(run) = self.run.take() {
+            let text = self.raster(&run.text, &run.style);
+            self.line.push(Piece {
+                x: run.x,
+                y: 0.0,
+                kind: PieceKind::Text(text, run.style.color),
+            });
+        }
+    }
+
+    fn finish_line(&mut self) {
+        self.flush();
+        let offset = match self.bounds {
+            Some((width, Align::Center)) => (width - self.line_width) / 2.0,
+            Some((width, Align::Right)) => width - self.line_width,
+            Some((_, Align::Left)) | None => 0.0,
+        };
+        let y = self.line_count as f32 * self.line_height;
+        for mut piece in self.line.drain(..) {
+            piece.x += offset;
+            piece.y += y;
+            self.pieces.push(piece);
+        }
+        self.width = self.width.max(self.line_width);
+        self.line_count += 1;
+        self.line_width = 0.0;
+        self.x = 0.0;
+    }
+}
+
+fn parse(contents: &str, markup: bool) -> Vec<Span> {
+    let mut spans = Vec::new();
+    if !markup {
+        push_text(&mut spans, contents, &Style::default());
+        return spans;
+    }
+    let mut bold = 0;
+    let mut colors = Vec::new();
+    let mut text = String::new();
+    let mut rest = contents;
+    while let Some(start) = rest.find('[') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("[[") {
+            text.push('[');
+            rest = after;
+            continue;
+        }
+        let end = match rest.find(']') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        let style = Style { bold: bold > 0, color: colors.last().copied() };
+        let icon = Icon::from_name(tag);
+        let color = tag.strip_prefix("color=").map(parse_color);
+        let known = matches!(tag, "b" | "/b" | "/color") || icon.is_some() || color.is_some();
+        if known {
+            push_text(&mut spans, &std::mem::take(&mut text), &style);
+        } else {
+            text.push_str(&rest[..=end]);
+        }
+        match tag {
+            "b" => bold += 1,
+            "/b" => bold -= i32::from(bold > 0),
+            "/color" => { colors.pop(); }
+            _ => {}
+        }
+        if let Some(icon) = icon {
+            spans.push(Span::Icon(icon));
+        }
+        if let Some(color) = color {
+            colors.push(color);
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    let style = Style { bold: bold > 0, color: colors.last().copied() };
+    push_text(&mut spans, &text, &style);
+    spans
+}
+
+fn push_text(spans: &mut Vec<Span>, text: &str, style: &Style) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            spans.push(Span::Newline);
+        }
+        if !line.is_empty() {
+            spans.push(Span::Text(line.to_owned(), style.clone()));
+        }
+    }
+}
+
+fn parse_color(name: &str) -> Color {
+    match name {
+        "red" => Color::new(0.85, 0.1, 0.1, 1.0),
+        "green" => Color::new(0.1, 0.65, 0.1, 1.0),
+        "blue" => Color::new(0.15, 0.3, 0.9, 1.0),
+        "gold" => Color::new(0.85, 0.65, 0.1, 1.0),
+        "grey" => Color::new(0.5, 0.5, 0.5, 1.0),
+        "white" => graphics::WHITE,
+        "black" => graphics::BLACK,
+        _ => {
+            let hex = name.strip_prefix('#').and_then(|hex| u32::from_str_radix(hex, 16).ok());
+            match hex {
+                Some(rgb) if name.len() == 7 => Color::from_rgb_u32(rgb),
+                _ => {
+                    eprintln!("unknown text colour: {}", name);
+                    graphics::BLACK
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str, bold: bool, color: Option<&str>) -> Span {
+        Span::Text(text.to_owned(), Style { bold, color: color.map(parse_color) })
+    }
+
+    #[test]
+    fn nested_tags() {
+        assert_eq!(parse("[b]a [color=red]b[/color] c[/b] d", true), vec![
+            text("a ", true, None),
+            text("b", true, Some("red")),
+            text(" c", true, None),
+            text(" d", false, None),
+        ]);
+        assert_eq!(parse("[color=red]a[color=blue]b[/color]c[/color]", true), vec![
+            text("a", false, Some("red")),
+            text("b", false, Some("blue")),
+            text("c", false, Some("red")),
+        ]);
+    }
+
+    #[test]
+    fn icons() {
+        assert_eq!(parse("hit [sword] 3", true), vec![
+            text("hit ", false, None),
+            Span::Icon(Icon::SWORD),
+            text(" 3", false, None),
+        ]);
+    }
+
+    #[test]
+    fn unknown_tags_are_text() {
+        assert_eq!(parse("a [foo] b", true), vec![text("a [foo] b", false, None)]);
+        // Closing tags that were never opened are dropped.
+        assert_eq!(parse("[/b]a[/color]", true), vec![text("a", false, None)]);
+    }
+
+    #[test]
+    fn double_bracket_escapes() {
+        assert_eq!(parse("a [[b] c", true), vec![text("a [b] c", false, None)]);
+        assert_eq!(parse("[[sword]", true), vec![text("[sword]", false, None)]);
+    }
+
+    #[test]
+    fn unterminated_bracket_is_text() {
+        assert_eq!(parse("a [b", true), vec![text("a [b", false, None)]);
+        assert_eq!(parse("[b]x [", true), vec![text("x [", true, None)]);
+    }
+
+    #[test]
+    fn plain_text_ignores_markup() {
+        assert_eq!(parse("[b]a\nb", false), vec![
+            text("[b]a", false, None),
+            Span::Newline,
+            text("b", false, None),
+        ]);
+    }
+}
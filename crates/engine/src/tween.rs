@@ -0,0 +1,276 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Easing curves, mapping linear progress from 0 to 1 onto eased progress.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Ease {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    /// Overshoots the target slightly before settling on it.
+    BackOut,
+}
+
+impl Ease {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::QuadInOut if t < 0.5 => 2.0 * t * t,
+            Ease::QuadInOut => 1.0 - (2.0 - 2.0 * t).powi(2) / 2.0,
+            Ease::CubicIn => t * t * t,
+            Ease::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Ease::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Ease::CubicInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+            Ease::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Ease::SineOut => (t * PI / 2.0).sin(),
+            Ease::SineInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Ease::BackOut => {
+                let c = 1.70158;
+                1.0 + (c + 1.0) * (t - 1.0).powi(3) + c * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+/// Values that can be tweened.
+pub trait Lerp: Copy {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: f32, t: f32) -> f32 {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(self, to: (f32, f32), t: f32) -> (f32, f32) {
+        (self.0.lerp(to.0, t), self.1.lerp(to.1, t))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment<T> {
+    to: T,
+    duration: f32,
+    ease: Ease,
+}
+
+/// A value animated through a sequence of segments, each one starting
+/// where the previous one ended.
+#[derive(Debug, Clone)]
+pub struct Tween<T> {
+    start: T,
+    segments: Vec<Segment<T>>,
+    elapsed: f32,
+}
+
+impl<T: Lerp> Tween<T> {
+    /// A tween that stays at `start` until segments are added.
+    pub fn new(start: T) -> Tween<T> {
+        Tween {
+            start,
+            segments: Vec::new(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn to(mut self, to: T, duration: f32, ease: Ease) -> Tween<T> {
+        self.segments.push(Segment { to, duration: duration.max(0.0), ease });
+        self
+    }
+
+    /// Holds the current end value for `duration`.
+    pub fn wait(self, duration: f32) -> Tween<T> {
+        let end = self.target();
+        self.to(end, duration, Ease::Linear)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.segments.iter().map(|s| s.duration).sum()
+    }
+
+    /// The value the tween ends at.
+    pub fn target(&self) -> T {
+        self.segments.last().map_or(self.start, |s| s.to)
+    }
+
+    /// The value at `time` seconds from the start, without advancing the
+    /// tween. Useful when progress is tracked elsewhere.
+    pub fn value_at(&self, mut time: f32) -> T {
+        let mut from = self.start;
+        for segment in &self.segments {
+            if time < segment.duration {
+                return from.lerp(segment.to, segment.ease.apply(time / segment.duration));
+            }
+            time -= segment.duration;
+            from = segment.to;
+        }
+        from
+    }
+
+    pub fn value(&self) -> T {
+        self.value_at(self.elapsed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration()
+    }
+
+    /// Advances the tween, returning how much of `dt` was left over after
+    /// it finished.
+    pub fn update(&mut self, dt: f32) -> f32 {
+        let duration = self.duration();
+        let left = (self.elapsed + dt - duration).max(0.0);
+        self.elapsed = (self.elapsed + dt).min(duration);
+        left
+    }
+
+    /// Replaces the tween with a single segment going from the current
+    /// value to `to`, so a moving value can change course smoothly.
+    pub fn retarget(&mut self, to: T, duration: f32, ease: Ease) {
+        *self = Tween::new(self.value()).to(to, duration, ease);
+    }
+
+    /// Turns the tween into an animation that writes its value into a
+    /// field of the animated object.
+    pub fn animate<S>(self, property: fn(&mut S) -> &mut T) -> Property<S, T> {
+        Property { tween: self, property }
+    }
+}
+
+/// An animation acting on an object of type `S`, which can be combined with
+/// others using `Sequence` and `Parallel`.
+pub trait Animation<S> {
+    /// Advances the animation, updating `target`. Returns the part of `dt`
+    /// left over once the animation has finished, or `None` while it is
+    /// still running.
+    fn update(&mut self, target: &mut S, dt: f32) -> Option<f32>;
+}
+
+/// See `Tween::animate`.
+pub struct Property<S, T> {
+    tween: Tween<T>,
+    property: fn(&mut S) -> &mut T,
+}
+
+impl<S, T: Lerp> Animation<S> for Property<S, T> {
+    fn update(&mut self, target: &mut S, dt: f32) -> Option<f32> {
+        let left = self.tween.update(dt);
+        *(self.property)(target) = self.tween.value();
+        if self.tween.is_finished() { Some(left) } else { None }
+    }
+}
+
+struct Wait(f32);
+
+impl<S> Animation<S> for Wait {
+    fn update(&mut self, _: &mut S, dt: f32) -> Option<f32> {
+        self.0 -= dt;
+        if self.0 <= 0.0 { Some(-self.0) } else { None }
+    }
+}
+
+type Callback<S> = Box<dyn FnOnce(&mut S)>;
+
+struct Call<S>(Option<Callback<S>>);
+
+impl<S> Animation<S> for Call<S> {
+    fn update(&mut self, target: &mut S, dt: f32) -> Option<f32> {
+        if let Some(callback) = self.0.take() {
+            callback(target);
+        }
+        Some(dt)
+    }
+}
+
+/// Runs animations one after another.
+pub struct Sequence<S> {
+    steps: VecDeque<Box<dyn Animation<S>>>,
+}
+
+impl<S: 'static> Sequence<S> {
+    pub fn new() -> Sequence<S> {
+        Sequence { steps: VecDeque::new() }
+    }
+
+    pub fn then(mut self, step: impl Animation<S> + 'static) -> Sequence<S> {
+        self.steps.push_back(Box::new(step));
+        self
+    }
+
+    pub fn wait(self, duration: f32) -> Sequence<S> {
+        self.then(Wait(duration))
+    }
+
+    /// Calls `callback` once every step added before it has finished.
+    pub fn call(self, callback: impl FnOnce(&mut S) + 'static) -> Sequence<S> {
+        self.then(Call(Some(Box::new(callback))))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl<S: 'static> Default for Sequence<S> {
+    fn default() -> Sequence<S> {
+        Sequence::new()
+    }
+}
+
+impl<S> Animation<S> for Sequence<S> {
+    fn update(&mut self, target: &mut S, mut dt: f32) -> Option<f32> {
+        while let Some(step) = self.steps.front_mut() {
+            dt = step.update(target, dt)?;
+            self.steps.pop_front();
+        }
+        Some(dt)
+    }
+}
+
+/// Runs animations side by side, finishing when the longest one does.
+pub struct Parallel<S> {
+    animations: Vec<Box<dyn Animation<S>>>,
+}
+
+impl<S: 'static> Parallel<S> {
+    pub fn new() -> Parallel<S> {
+        Parallel { animations: Vec::new() }
+    }
+
+    pub fn with(mut self, animation: impl Animation<S> + 'static) -> Parallel<S> {
+        self.animations.push(Box::new(animation));
+        self
+    }
+}
+
+impl<S: 'static> Default for Parallel<S> {
+    fn default() -> Parallel<S> {
+        Parallel::new()
+    }
+}
+
+impl<S> Animation<S> for Parallel<S> {
+    fn update(&mut self, target: &mut S, dt: f32) -> Option<f32> {
+        let mut left = dt;
+        self.animations.retain_mut(|animation| match animation.update(target, dt) {
+            Some(rest) => {
+                left = left.min(rest);
+                false
+            }
+            None => true,
+        });
+        if self.animations.is_empty() { Some(left) } else { None }
+    }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use engine::{Action, Ctx, InputButton, InputMap, KeyCode};
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum GameAction {
+    StartWalk,
+    OpenDeck,
+    OpenDiscards,
+    Pause,
+    FastForward,
+    Undo,
+    Redo,
+    ClearCells,
+    Mulligan,
+    StepMode,
+    Back,
+}
+
+impl GameAction {
+    pub const ALL: &'static [GameAction] = &[
+        GameAction::StartWalk,
+        GameAction::OpenDeck,
+        GameAction::OpenDiscards,
+        GameAction::Pause,
+        GameAction::FastForward,
+        GameAction::Undo,
+        GameAction::Redo,
+        GameAction::ClearCells,
+        GameAction::Mulligan,
+        GameAction::StepMode,
+        GameAction::Back,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameAction::StartWalk => "Start walk",
+            GameAction::OpenDeck => "Open deck",
+            GameAction::OpenDiscards => "Open discards",
+            GameAction::Pause => "Pause",
+            GameAction::FastForward => "Fast forward",
+            GameAction::Undo => "Undo placement",
+            GameAction::Redo => "Redo placement",
+            GameAction::ClearCells => "Return all to hand",
+            GameAction::Mulligan => "Redraw cards",
+            GameAction::StepMode => "Step mode",
+            GameAction::Back => "Back / cancel",
+        }
+    }
+
+    /// The gamepad action that also triggers this, if there is one.
+    fn gamepad_action(self) -> Option<Action> {
+        match self {
+            GameAction::StartWalk => Some(Action::StartWalk),
+            GameAction::OpenDeck => Some(Action::OpenDeck),
+            GameAction::OpenDiscards => Some(Action::OpenDiscards),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    keys: Vec<(GameAction, KeyCode)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            keys: vec![
+                (GameAction::StartWalk, KeyCode::Space),
+                (GameAction::OpenDeck, KeyCode::D),
+                (GameAction::OpenDiscards, KeyCode::R),
+                (GameAction::Pause, KeyCode::Escape),
+                (GameAction::FastForward, KeyCode::F),
+                (GameAction::Undo, KeyCode::Z),
+                (GameAction::Redo, KeyCode::Y),
+                (GameAction::ClearCells, KeyCode::C),
+                (GameAction::Mulligan, KeyCode::M),
+                (GameAction::StepMode, KeyCode::F9),
+                (GameAction::Back, KeyCode::Escape),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: GameAction) -> KeyCode {
+        self.keys
+            .iter()
+            .find(|&&(a, _)| a == action)
+            .map(|&(_, key)| key)
+            .expect("action not bound")
+    }
+
+    /// Binds the key to the action. If another action already used that
+    /// key, it gets the key that this action had before. `Back` is only
+    /// used in menus and to cancel, so it can share a key with the others.
+    pub fn set_key(&mut self, action: GameAction, key: KeyCode) {
+        let old = self.key(action);
+        let shared = |a: GameAction| a == GameAction::Back || action == GameAction::Back;
+        for (a, k) in &mut self.keys {
+            if *a == action {
+                *k = key;
+            } else if *k == key && !shared(*a) {
+                *k = old;
+            }
+        }
+    }
+
+    /// Engine input map with the bound back key, applied again whenever the
+    /// bindings change so that menus follow rebinding.
+    pub fn input_map(&self) -> InputMap {
+        let mut map = InputMap::default();
+        map.bind(InputButton::Key(self.key(GameAction::Back)), Action::Back);
+        map
+    }
+
+    pub fn is_click(&self, ctx: &Ctx<'_>, action: GameAction) -> bool {
+        ctx.is_key_click(self.key(action)) ||
+            action.gamepad_action().is_some_and(|a| ctx.is_action_click(a))
+    }
+
+    pub fn is_pressed(&self, ctx: &Ctx<'_>, action: GameAction) -> bool {
+        ctx.is_key_pressed(self.key(action)) ||
+            action.gamepad_action().is_some_and(|a| ctx.is_action_pressed(a))
+    }
+}
+
+// Stored as key names, because key codes can't be serialized directly.
+impl Serialize for KeyBindings {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names = self.keys
+            .iter()
+            .map(|&(action, key)| (action, key_name(key).unwrap_or("")))
+            .collect::<Vec<_>>();
+        names.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<(GameAction, String)>::deserialize(deserializer)?;
+        let mut bindings = KeyBindings::default();
+        for (action, name) in names {
+            if let Some(key) = key_from_name(&name) {
+                bindings.set_key(action, key);
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|&&(k, _)| k == key).map(|&(_, name)| name)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES.iter().find(|&&(_, n)| n == name).map(|&(key, _)| key)
+}
+
+/// Keys that can be bound to an action. F1, F12 and ` are left out as
+/// they toggle the developer tools and captures, and so are the arrows,
+/// Enter, Q and E, which the engine's input map already uses.
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::F2, "F2"),
+    (KeyCode::F3, "F3"),
+    (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"),
+    (KeyCode::F6, "F6"),
+    (KeyCode::F7, "F7"),
+    (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"),
+    (KeyCode::F10, "F10"),
+    (KeyCode::F11, "F11"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Escape, "Escape"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Back, "Backspace"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "Page Up"),
+    (KeyCode::PageDown, "Page Down"),
+    (KeyCode::LShift, "Left Shift"),
+    (KeyCode::RShift, "Right Shift"),
+    (KeyCode::LControl, "Left Ctrl"),
+    (KeyCode::RControl, "Right Ctrl"),
+    (KeyCode::LAlt, "Left Alt"),
+    (KeyCode::RAlt, "Right Alt"),
+    (KeyCode::Minus, "-"),
+    (KeyCode::Equals, "="),
+    (KeyCode::Comma, ","),
+    (KeyCode::Period, "."),
+    (KeyCode::Slash, "/"),
+    (KeyCode::Semicolon, ";"),
+    (KeyCode::Apostrophe, "'"),
+    (KeyCode::LBracket, "["),
+    (KeyCode::RBracket, "]"),
+    (KeyCode::Backslash, "\\"),
+    (KeyCode::Numpad0, "Numpad 0"),
+    (KeyCode::Numpad1, "Numpad 1"),
+    (KeyCode::Numpad2, "Numpad 2"),
+    (KeyCode::Numpad3, "Numpad 3"),
+    (KeyCode::Numpad4, "Numpad 4"),
+    (KeyCode::Numpad5, "Numpad 5"),
+    (KeyCode::Numpad6, "Numpad 6"),
+    (KeyCode::Numpad7, "Numpad 7"),
+    (KeyCode::Numpad8, "Numpad 8"),
+    (KeyCode::Numpad9, "Numpad 9"),
+];
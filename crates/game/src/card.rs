@@ -68,3 +68,14 @@ pub struct Decks {
     pub treasure: Vec<Card>,
     pub boss: Card,
 }
+
+impl Decks {
+    pub fn find(&self, id: &str) -> Option<&Card> {
+        self.draw
+            .iter()
+            .chain(&self.trap)
+            .chain(&self.treasure)
+            .chain(std::iter::once(&self.boss))
+            .find(|c| c.id == id)
+    }
+}
@@ -0,0 +1,202 @@
+use engine::{Action, Ctx, FrameRenderer, KeyCode, Result, ScreenText, SCREEN_HEIGHT, SCREEN_WIDTH};
+use engine::ggez::graphics::WHITE;
+use crate::GameData;
+use crate::views::ViewStack;
+
+const OVERLAY_KEY: KeyCode = KeyCode::F1;
+const CONSOLE_KEY: KeyCode = KeyCode::Grave;
+const TEXT_SIZE: f32 = 24.0;
+const LOG_LINES: usize = 8;
+const ACTIONS: [Action; 12] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::Confirm,
+    Action::Back,
+    Action::Pause,
+    Action::NextCard,
+    Action::PreviousCard,
+    Action::StartWalk,
+    Action::OpenDeck,
+    Action::OpenDiscards,
+];
+const HELP: &str = "\
+commands:
+  card <id>        put a card into the hand
+  health <amount>  set player health
+  coins <amount>   set player coins
+  level <level>    skip to a later level
+  buff <id>        give the boss a buff card
+  traps            list the trap deck in dealing order";
+
+/// Developer overlay and console, only compiled in with the `dev` feature.
+pub struct DevTools {
+    overlay: bool,
+    console: bool,
+    input: String,
+    log: Vec<String>,
+    // What the overlay shows is gathered on update, when the input state
+    // is at hand.
+    actions: String,
+    overlay_text: ScreenText,
+    console_text: ScreenText,
+}
+
+impl DevTools {
+    pub fn new() -> DevTools {
+        DevTools {
+            overlay: false,
+            console: false,
+            input: String::new(),
+            log: Vec::new(),
+            actions: String::new(),
+            overlay_text: ScreenText::new("", TEXT_SIZE),
+            console_text: ScreenText::new("", TEXT_SIZE),
+        }
+    }
+
+    /// Returns whether the console is open, in which case the game should
+    /// not see any input.
+    pub fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, views: &mut ViewStack) -> bool {
+        if ctx.is_key_click(OVERLAY_KEY) {
+            self.overlay = !self.overlay;
+            ctx.set_debug_overlay(self.overlay || data.options.show_fps);
+        }
+        self.actions = ACTIONS
+            .iter()
+            .filter(|&&action| ctx.is_action_pressed(action))
+            .map(|action| format!("{:?}", action))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if ctx.is_key_click(CONSOLE_KEY) {
+            self.console = !self.console;
+            return true;
+        }
+        if !self.console {
+            return false;
+        }
+        if ctx.is_key_click(KeyCode::Escape) {
+            self.console = false;
+        } else if ctx.is_key_click(KeyCode::Back) {
+            self.input.pop();
+        } else if ctx.is_key_click(KeyCode::Return) {
+            let command = std::mem::take(&mut self.input);
+            self.log(format!("> {}", command));
+            match run_command(data, views, &command) {
+                Ok(output) => self.log(output),
+                Err(e) => self.log(format!("error: {}", e)),
+            }
+        }
+        self.input.extend(ctx.text_input().chars().filter(|&c| c != '`'));
+        true
+    }
+
+    fn log(&mut self, text: String) {
+        self.log.extend(text.lines().filter(|line| !line.is_empty()).map(str::to_owned));
+        let excess = self.log.len().saturating_sub(LOG_LINES);
+        self.log.drain(..excess);
+    }
+
+    pub fn draw(&mut self, renderer: &mut FrameRenderer<'_>, views: &mut ViewStack) -> Result {
+        if self.overlay {
+            let (mouse_x, mouse_y) = renderer.ctx().mouse_position();
+            let action_state = views
+                .game_state()
+                .map(|state| state.action_state())
+                .unwrap_or_else(|| "-".to_owned());
+            let stack = views
+                .view_names()
+                .into_iter()
+                .map(short_name)
+                .collect::<Vec<_>>()
+                .join(" > ");
+            self.overlay_text.set_contents(&format!(
+                "Mouse: {:.0}, {:.0}\nActions: {}\nAction state: {}\nViews: {}",
+                mouse_x,
+                mouse_y,
+                self.actions,
+                action_state,
+                stack,
+            ));
+            let (width, height) = renderer.text_dimensions(&mut self.overlay_text);
+            let x = SCREEN_WIDTH - width - 20.0;
+            renderer.draw_shade(x - 10.0, 10.0, width + 20.0, height + 10.0, 0.7)?;
+            renderer.draw_text(&mut self.overlay_text, x, 15.0, WHITE)?;
+        }
+        if self.console {
+            let mut lines = self.log.clone();
+            lines.push(format!("> {}_", self.input));
+            self.console_text.set_contents(&lines.join("\n"));
+            let height = (LOG_LINES + 1) as f32 * TEXT_SIZE * 1.25 + 20.0;
+            renderer.draw_shade(0.0, SCREEN_HEIGHT - height, SCREEN_WIDTH, height, 0.8)?;
+            let (_, text_height) = renderer.text_dimensions(&mut self.console_text);
+            renderer.draw_text(&mut self.console_text, 20.0, SCREEN_HEIGHT - text_height - 10.0, WHITE)?;
+        }
+        Ok(())
+    }
+}
+
+fn run_command(data: &GameData, views: &mut ViewStack, command: &str) -> std::result::Result<String, String> {
+    let mut words = command.split_whitespace();
+    let name = match words.next() {
+        Some(name) => name,
+        None => return Ok(String::new()),
+    };
+    let argument = words.next();
+    if name == "help" {
+        return Ok(HELP.to_owned());
+    }
+    let state = views.game_state().ok_or_else(|| "no game running".to_owned())?;
+    let card = |id: Option<&str>| {
+        let id = id.ok_or_else(|| "expected a card id".to_owned())?;
+        data.decks.find(id).cloned().ok_or_else(|| format!("no card with id {}", id))
+    };
+    let number = |value: Option<&str>| {
+        let value = value.ok_or_else(|| "expected a number".to_owned())?;
+        value.parse::<u32>().map_err(|_| format!("not a number: {}", value))
+    };
+    match name {
+        "card" => {
+            let card = card(argument)?;
+            let output = format!("added {} to the hand", card.id);
+            state.spawn_card(card);
+            Ok(output)
+        }
+        "health" => state.set_health(number(argument)?).map(|()| "health set".to_owned()),
+        "coins" => state.set_coins(number(argument)?).map(|()| "coins set".to_owned()),
+        "level" => {
+            let level = number(argument)? as usize;
+            state.skip_to_level(level).map(|()| format!("skipped to level {}", level))
+        }
+        "buff" => {
+            let card = card(argument)?;
+            let output = format!("gave the boss {}", card.id);
+            state.buff_boss(card).map(|()| output)
+        }
+        "traps" => match state.trap_order() {
+            order if order.is_empty() => Ok("the trap deck is empty".to_owned()),
+            order => Ok(order.join(", ")),
+        },
+        _ => Err(format!("unknown command: {}, try help", name)),
+    }
+}
+
+/// Strips module paths from a type name, keeping generic parameters.
+fn short_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            short.truncate(segment_start);
+        } else {
+            short.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = short.len();
+            }
+        }
+    }
+    short
+}
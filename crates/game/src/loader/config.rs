@@ -7,6 +7,10 @@ pub struct Card {
     pub title: String,
     pub description: Vec<String>,
     pub effect: CardEffect,
+    /// Name of the frame from `frames.ron`. When missing, it is picked by
+    /// the kind of effect.
+    #[serde(default)]
+    pub frame: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -59,3 +63,64 @@ pub struct Decks {
     pub treasure: HashMap<String, u32>,
     pub boss: String,
 }
+
+/// How a card is laid out, drawn under everything that is specific to the
+/// card. Positions are in card pixels.
+#[derive(Deserialize, Debug)]
+pub struct Frame {
+    pub layers: Vec<Layer>,
+    pub icon: IconRegion,
+    pub title: TextRegion,
+    pub description: TextRegion,
+    #[serde(default)]
+    pub badges: Vec<Badge>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Layer {
+    pub image: String,
+    /// Multiplied with the image colors, as (r, g, b).
+    #[serde(default = "white")]
+    pub tint: (f32, f32, f32),
+}
+
+fn white() -> (f32, f32, f32) {
+    (1.0, 1.0, 1.0)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IconRegion {
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+}
+
+/// Text is centered horizontally in the region, and made smaller if it
+/// does not fit its height.
+#[derive(Deserialize, Debug)]
+pub struct TextRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub size: f32,
+}
+
+/// An icon with a number from the card effect under it. Skipped on cards
+/// that don't have the stat.
+#[derive(Deserialize, Debug)]
+pub struct Badge {
+    pub stat: Stat,
+    pub icon: String,
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    pub text_size: f32,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub enum Stat {
+    Attack,
+    Health,
+    Price,
+}
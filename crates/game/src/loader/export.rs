@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::Path;
+use engine::TextureSource;
+use engine::ggez::{Context, GameError, GameResult};
+use engine::image::{imageops, Rgba, RgbaImage};
+use super::{config, CardRenderer, CARD_HEIGHT, CARD_WIDTH};
+
+// Sheets are A4 pages printed at 300 DPI, with cards at the usual
+// 63 x 88 mm size.
+const DPI: f32 = 300.0;
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const PRINTED_CARD_WIDTH: f32 = 63.0;
+const MARGIN: f32 = 8.0;
+// Space between cards so that they can be cut apart.
+const GAP: f32 = 1.0;
+
+fn mm_to_pixels(mm: f32) -> u32 {
+    (mm / 25.4 * DPI).round() as u32
+}
+
+struct ExportedCard<'a> {
+    renderer: &'a CardRenderer,
+    card: &'a config::Card,
+}
+
+impl TextureSource for ExportedCard<'_> {
+    fn draw(&self, ctx: &mut Context, resolution: f32) -> GameResult {
+        self.renderer.draw_card(ctx, self.card, resolution)
+    }
+}
+
+/// Writes an image of every card to `dir`, along with print-and-play
+/// sheets that have every deck from `decks.ron` laid out on A4 pages.
+pub fn export_cards(ctx: &mut Context, dir: &Path) -> GameResult {
+    let renderer = super::load_card_renderer(ctx)?;
+    let resolution = mm_to_pixels(PRINTED_CARD_WIDTH) as f32 / CARD_WIDTH;
+    std::fs::create_dir_all(dir)?;
+
+    let decks = super::read_decks();
+    let mut images = HashMap::new();
+    for file in super::read_cards(&decks)? {
+        let card = ExportedCard { renderer: &renderer, card: &file.card };
+        let image = engine::render_pixels(ctx, &card, CARD_WIDTH, CARD_HEIGHT, resolution)?;
+        let path = dir.join(format!("{}.png", file.name));
+        println!("writing {}", path.display());
+        image.save(&path)?;
+        images.insert(file.name, image);
+    }
+
+    let mut sheets = Sheets::new();
+    let boss = std::iter::once((&decks.boss, &1));
+    let deck_lists: [Vec<(&String, &u32)>; 4] = [
+        decks.draw.iter().collect(),
+        decks.trap.iter().collect(),
+        decks.treasure.iter().collect(),
+        boss.collect(),
+    ];
+    for mut deck in deck_lists {
+        deck.sort();
+        // Every deck starts on a new page, so the decks are easy to keep
+        // apart after cutting.
+        sheets.new_page();
+        for (id, &count) in deck {
+            let image = images.get(id).ok_or_else(|| GameError::ResourceLoadError(
+                format!("card not defined: {}", id)
+            ))?;
+            for _ in 0..count {
+                sheets.add(image);
+            }
+        }
+    }
+    for (index, page) in sheets.pages.iter().enumerate() {
+        let path = dir.join(format!("sheet-{}.png", index + 1));
+        println!("writing {}", path.display());
+        page.save(&path)?;
+    }
+    Ok(())
+}
+
+/// Lays out cards on pages in rows, left to right.
+struct Sheets {
+    pages: Vec<RgbaImage>,
+    next: usize,
+}
+
+impl Sheets {
+    fn new() -> Sheets {
+        Sheets { pages: Vec::new(), next: 0 }
+    }
+
+    fn new_page(&mut self) {
+        if self.pages.last().is_none() || self.next > 0 {
+            let (width, height) = (mm_to_pixels(PAGE_WIDTH), mm_to_pixels(PAGE_HEIGHT));
+            self.pages.push(RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255])));
+            self.next = 0;
+        }
+    }
+
+    fn add(&mut self, card: &RgbaImage) {
+        let (page_width, page_height) = (mm_to_pixels(PAGE_WIDTH), mm_to_pixels(PAGE_HEIGHT));
+        let (margin, gap) = (mm_to_pixels(MARGIN), mm_to_pixels(GAP));
+        let columns = ((page_width - margin * 2 + gap) / (card.width() + gap)) as usize;
+        let rows = ((page_height - margin * 2 + gap) / (card.height() + gap)) as usize;
+        if self.next == columns * rows {
+            self.new_page();
+        }
+        // The grid is centered on the page.
+        let left = (page_width - columns as u32 * (card.width() + gap) + gap) / 2;
+        let top = (page_height - rows as u32 * (card.height() + gap) + gap) / 2;
+        let x = left + (self.next % columns) as u32 * (card.width() + gap);
+        let y = top + (self.next / columns) as u32 * (card.height() + gap);
+        let page = self.pages.last_mut().expect("no page to add cards to");
+        imageops::overlay(page, card, x, y);
+        self.next += 1;
+    }
+}
@@ -1,58 +1,57 @@
 pub mod config;
+pub mod export;
 
 use std::collections::HashMap;
+use std::hash::Hasher;
+use std::io::Read;
 use std::path::PathBuf;
+use std::rc::Rc;
 use serde::Deserialize;
-use engine::{Texture, Textures};
+use engine::{AtlasBuilder, Fonts, ScreenText, TextureSource, Textures};
 use engine::ggez::{self, Context, GameResult};
-use engine::ggez::graphics::{Canvas, Image, Text, TextFragment};
+use engine::ggez::graphics::{Align, Color, DrawParam, Image};
 use crate::card;
-use ggez::graphics::{Scale, Rect};
+use ggez::graphics::Rect;
 
 pub struct GameResources {
     pub decks: card::Decks,
     pub renderer: engine::Renderer,
 }
 
-#[derive(Default)]
-struct TextureSet {
-    textures: Vec<Image>,
-}
-
-impl TextureSet {
-    fn add(&mut self, texture: Image) -> Texture {
-        self.textures.push(texture);
-        Texture::new(self.textures.len() as u32 - 1)
-    }
-}
-
-const CARD_WIDTH: u16 = 320;
-const CARD_HEIGHT: u16 = 448;
+const CARD_WIDTH: f32 = 320.0;
+const CARD_HEIGHT: f32 = 448.0;
+/// Part of every cached card's key, bump it when cards are drawn
+/// differently so that old renders are not used.
+const CACHE_VERSION: u32 = 1;
 
 pub fn load_resources(ctx: &mut Context) -> GameResult<GameResources> {
-    let mut texture_set = TextureSet::default();
-    let icons = Image::new(ctx, "/temp.png")?;
-    let mut icons = make_transparent(ctx, &icons)?;
-    icons.set_filter(ggez::graphics::FilterMode::Nearest);
-    let card_base = Image::new(ctx, "/card-base.png")?;
+    let mut atlas = AtlasBuilder::new();
+    if let Some(dir) = engine::user_cache_dir() {
+        atlas = atlas.with_cache(dir.join("cards"));
+    }
+    let card_renderer = Rc::new(load_card_renderer(ctx)?);
 
-    let card_back = Image::new(ctx, "/card-back.png")?;
-    let card_back = render_card_back(ctx, &card_base, &card_back)?;
-    let card_back = texture_set.add(card_back);
+    let card_back = CardBackSource {
+        base: Image::new(ctx, "/card-base.png")?,
+        back: Image::new(ctx, "/card-back.png")?,
+    };
+    let card_back = atlas.add_source(CARD_WIDTH, CARD_HEIGHT, card_back);
 
-    let button = texture_set.add(Image::new(ctx, "/button/regular.png")?);
-    let button_hover = texture_set.add(Image::new(ctx, "/button/hover.png")?);
-    let button_selected = texture_set.add(Image::new(ctx, "/button/selected.png")?);
+    let button = atlas.add_image(Image::new(ctx, "/button/regular.png")?);
+    let button_hover = atlas.add_image(Image::new(ctx, "/button/hover.png")?);
+    let button_selected = atlas.add_image(Image::new(ctx, "/button/selected.png")?);
 
-    let cards = load_cards(ctx, &card_base, &icons, &mut texture_set.textures)?;
-    let decks = load_decks(&cards);
+    let deck_config = read_decks();
+    let cards = load_cards(&card_renderer, &deck_config, &mut atlas)?;
+    let decks = load_decks(&deck_config, &cards);
 
-    let renderer = engine::Renderer::new(icons, texture_set.textures, Textures {
+    let atlas = atlas.build(ctx)?;
+    let renderer = engine::Renderer::new(card_renderer.icons.clone(), atlas, Textures {
         card_back,
         button,
         button_hover,
         button_selected,
-    });
+    }, card_renderer.fonts);
 
     Ok(GameResources {
         decks,
@@ -60,12 +59,95 @@ pub fn load_resources(ctx: &mut Context) -> GameResult<GameResources> {
     })
 }
 
-fn load_cards(ctx: &mut Context, base: &Image, icons: &Image, textures: &mut Vec<Image>) -> GameResult<HashMap<String, card::Card>> {
-    let mut renderer = CardRenderer {
+fn load_card_renderer(ctx: &mut Context) -> GameResult<CardRenderer> {
+    let icons = Image::new(ctx, "/temp.png")?;
+    let mut icons = make_transparent(ctx, &icons)?;
+    icons.set_filter(ggez::graphics::FilterMode::Nearest);
+    // Cached cards have to be rendered again if any file they are drawn
+    // from changes.
+    let mut hash = Fnv::new();
+    hash.write_u32(CACHE_VERSION);
+    hash_file(ctx, "/temp.png", &mut hash)?;
+    for &path in Fonts::FILES {
+        // Missing fonts are replaced with the built in one.
+        if ggez::filesystem::exists(ctx, path) {
+            hash_file(ctx, path, &mut hash)?;
+        }
+    }
+
+    let frames_ron = std::fs::read_to_string("./data/frames.ron")?;
+    hash.write(frames_ron.as_bytes());
+    let configs: HashMap<String, config::Frame> = ron::from_str(&frames_ron)
+        .map_err(|e| ggez::GameError::ResourceLoadError(
+            format!("could not deserialize frames: {}", e)
+        ))?;
+    let mut configs = configs.into_iter().collect::<Vec<_>>();
+    // Sorted so that files are hashed in the same order every time.
+    configs.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut frames = HashMap::new();
+    for (name, config) in configs {
+        let mut layers = Vec::new();
+        for layer in &config.layers {
+            hash_file(ctx, &layer.image, &mut hash)?;
+            let (r, g, b) = layer.tint;
+            layers.push((Image::new(ctx, &layer.image)?, Color::new(r, g, b, 1.0)));
+        }
+        for badge in &config.badges {
+            icon_index(&badge.icon);
+        }
+        frames.insert(name, CardFrame { layers, config });
+    }
+
+    Ok(CardRenderer {
         icons,
-        base,
-    };
-    let mut cards = HashMap::new();
+        fonts: Fonts::load(ctx),
+        frames,
+        hash,
+    })
+}
+
+fn hash_file(ctx: &mut Context, path: &str, hash: &mut Fnv) -> GameResult {
+    let mut bytes = Vec::new();
+    ggez::filesystem::open(ctx, path)?.read_to_end(&mut bytes)?;
+    hash.write(&bytes);
+    Ok(())
+}
+
+/// 64 bit FNV-1a. Cache keys outlive the game process, so unlike
+/// `DefaultHasher` the result has to be the same between runs and builds.
+#[derive(Clone)]
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+struct CardFile {
+    name: String,
+    /// The file contents, for telling when the card has changed.
+    ron: String,
+    card: config::Card,
+}
+
+/// Reads every card, with cards that don't name a frame getting one by the
+/// deck they are in.
+fn read_cards(decks: &config::Decks) -> GameResult<Vec<CardFile>> {
+    let mut cards = Vec::new();
     for entry in std::fs::read_dir("./data/cards")? {
         let entry = entry?;
         let path = entry.path();
@@ -73,25 +155,56 @@ fn load_cards(ctx: &mut Context, base: &Image, icons: &Image, textures: &mut Vec
         let name = name.file_stem().and_then(|s| s.to_str()).expect("bad card name");
         println!("loading card {}", path.display());
         let card_ron = std::fs::read_to_string(path)?;
-        let card: config::Card = ron::from_str(&card_ron)
+        let mut card: config::Card = ron::from_str(&card_ron)
             .map_err(|e| ggez::GameError::ResourceLoadError(
                 format!("could not deserialize card: {}", e)
             ))?;
-        let image = renderer.render_card(ctx, &card)?;
-        let texture = engine::Texture::new(textures.len() as u32);
-        textures.push(image);
-        cards.insert(name.to_owned(), card::Card {
-            id: name.to_owned(),
+        if card.frame.is_none() {
+            card.frame = Some(default_frame(name, &card.effect, decks).to_owned());
+        }
+        cards.push(CardFile {
+            name: name.to_owned(),
+            ron: card_ron,
+            card,
+        });
+    }
+    Ok(cards)
+}
+
+fn load_cards(
+    renderer: &Rc<CardRenderer>,
+    decks: &config::Decks,
+    atlas: &mut AtlasBuilder,
+) -> GameResult<HashMap<String, card::Card>> {
+    let mut cards = HashMap::new();
+    for file in read_cards(decks)? {
+        // Checked here so that a missing frame is found before drawing.
+        renderer.frame(&file.card)?;
+        let effect = convert_effect(&file.card.effect);
+        let mut hash = renderer.hash.clone();
+        hash.write(file.ron.as_bytes());
+        // The frame can come from the deck, which is not in the card file.
+        hash.write(file.card.frame.as_deref().unwrap_or_default().as_bytes());
+        let texture = atlas.add_source(CARD_WIDTH, CARD_HEIGHT, CardSource {
+            renderer: renderer.clone(),
+            card: file.card,
+            key: hash.finish(),
+        });
+        cards.insert(file.name.clone(), card::Card {
+            id: file.name,
             texture,
-            effect: convert_effect(&card.effect),
+            effect,
         });
     }
     Ok(cards)
 }
 
-fn load_decks(cards: &HashMap<String, card::Card>) -> card::Decks {
+fn read_decks() -> config::Decks {
     let decks = std::fs::read_to_string("./data/decks.ron").unwrap();
-    let config: config::Decks = ron::from_str(&decks).unwrap();
+    ron::from_str(&decks).unwrap()
+}
+
+fn load_decks(config: &config::Decks, cards: &HashMap<String, card::Card>) -> card::Decks {
     let mut draw = Vec::new();
     let mut trap = Vec::new();
     let mut treasure = Vec::new();
@@ -130,30 +243,59 @@ fn load_decks(cards: &HashMap<String, card::Card>) -> card::Decks {
     card::Decks { draw, trap, treasure, boss }
 }
 
-struct CardRenderer<'a> {
-    icons: &'a Image,
-    base: &'a Image,
+struct CardRenderer {
+    icons: Image,
+    fonts: Fonts,
+    frames: HashMap<String, CardFrame>,
+    /// Hash of the files that all cards are drawn from.
+    hash: Fnv,
+}
+
+struct CardFrame {
+    /// Images drawn over each other, with their tint.
+    layers: Vec<(Image, Color)>,
+    config: config::Frame,
+}
+
+/// Draws a card again when the window resolution changes, so that its
+/// text stays sharp.
+struct CardSource {
+    renderer: Rc<CardRenderer>,
+    card: config::Card,
+    /// Hash of the card file and the files the card is drawn from.
+    key: u64,
+}
+
+impl TextureSource for CardSource {
+    fn draw(&self, ctx: &mut Context, resolution: f32) -> GameResult {
+        self.renderer.draw_card(ctx, &self.card, resolution)
+    }
+
+    fn cache_key(&self) -> Option<u64> {
+        Some(self.key)
+    }
+}
+
+struct CardBackSource {
+    base: Image,
+    back: Image,
+}
+
+impl TextureSource for CardBackSource {
+    fn draw(&self, ctx: &mut Context, _resolution: f32) -> GameResult {
+        ggez::graphics::draw(ctx, &self.base, ggez::graphics::DrawParam::new()
+            .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 })
+            .dest([0.0, 0.0]))?;
+        ggez::graphics::draw(ctx, &self.back, ggez::graphics::DrawParam::new()
+            .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 })
+            .dest([0.0, 0.0]))
+    }
 }
 
 fn icon_index(icon: &str) -> u32 {
-    match icon {
-        "sword" => 4,
-        "heart" => 5,
-        "shield" => 6,
-        "beholder" => 8,
-        "coin" => 12,
-        "cross" => 13,
-        "bang" => 14,
-        "blue-beholder" => 16,
-        "green-heart" => 17,
-        "broken" => 18,
-        "black" => 21,
-        "disarm" => 22,
-        "red-sword" => 23,
-        "bow" => 24,
-        "fighter-2" => 25,
-        "chicken" => 26,
-        _ => panic!("invalid icon: {:?}", icon)
+    match engine::Icon::from_name(icon) {
+        Some(icon) => icon.index(),
+        None => panic!("invalid icon: {:?}", icon),
     }
 }
 
@@ -223,105 +365,118 @@ fn convert_buff(buff: &config::Buff) -> card::Buff {
     }
 }
 
-impl CardRenderer<'_> {
-    fn render_card(&mut self, ctx: &mut Context, config: &config::Card) -> GameResult<Image> {
-        let card_render = ggez::graphics::Canvas::new(
-            ctx,
-            CARD_WIDTH,
-            CARD_HEIGHT,
-            ggez::conf::NumSamples::One,
-        )?;
-        ggez::graphics::set_canvas(ctx, Some(&card_render));
-        ggez::graphics::set_screen_coordinates(ctx, Rect {
-            x: 0.0,
-            y: 0.0,
-            w: f32::from(CARD_WIDTH),
-            h: f32::from(CARD_HEIGHT),
-        })?;
-        ggez::graphics::draw(ctx, self.base, ggez::graphics::DrawParam::new()
-            .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 })
-            .dest([0.0, 0.0]))?;
-        let icon = icon_index(&config.icon);
+impl CardRenderer {
+    fn frame(&self, card: &config::Card) -> GameResult<&CardFrame> {
+        let name = card.frame.as_deref().unwrap_or("default");
+        self.frames.get(name).ok_or_else(|| ggez::GameError::ResourceLoadError(
+            format!("frame not defined: {}", name)
+        ))
+    }
+
+    /// Draws the card in logical coordinates into its atlas region. Only
+    /// text has to be scaled by hand so that it is rasterised at
+    /// `resolution` times its size.
+    fn draw_card(&self, ctx: &mut Context, config: &config::Card, resolution: f32) -> GameResult {
+        let frame = self.frame(config)?;
+        for (image, tint) in &frame.layers {
+            ggez::graphics::draw(ctx, image, DrawParam::new()
+                .dest([0.0, 0.0])
+                .scale([
+                    CARD_WIDTH / f32::from(image.width()),
+                    CARD_HEIGHT / f32::from(image.height()),
+                ])
+                .color(*tint))?;
+        }
+        let layout = &frame.config;
+        self.draw_icon(ctx, &config.icon, layout.icon.x, layout.icon.y, layout.icon.size)?;
+        let title = |size| ScreenText::new(config.title.as_str(), size);
+        self.fit_text(ctx, title, &layout.title, resolution)
+            .draw_with(ctx, &self.fonts, &self.icons, (layout.title.x, layout.title.y), resolution, ggez::graphics::BLACK)?;
+        let description = |size| ScreenText::rich(config.description.join("\n"), size);
+        self.fit_text(ctx, description, &layout.description, resolution)
+            .draw_with(ctx, &self.fonts, &self.icons, (layout.description.x, layout.description.y), resolution, ggez::graphics::BLACK)?;
+        for badge in &layout.badges {
+            let value = match stat(&config.effect, badge.stat) {
+                Some(value) => value,
+                None => continue,
+            };
+            self.draw_icon(ctx, &badge.icon, badge.x, badge.y, badge.size)?;
+            ScreenText::rich(format!("[b]{}[/b]", value), badge.text_size)
+                .with_bounds(badge.size, Align::Center)
+                .draw_with(ctx, &self.fonts, &self.icons, (badge.x, badge.y + badge.size), resolution, ggez::graphics::BLACK)?;
+        }
+        Ok(())
+    }
+
+    /// Shrinks the text until it fits the region's height. Below half of its
+    /// size it would not be readable anyway, so then it is left to overflow.
+    fn fit_text(
+        &self,
+        ctx: &mut Context,
+        make_text: impl Fn(f32) -> ScreenText,
+        region: &config::TextRegion,
+        resolution: f32,
+    ) -> ScreenText {
+        let mut size = region.size;
+        loop {
+            let mut text = make_text(size).with_bounds(region.width, Align::Center);
+            let (_, height) = text.dimensions(ctx, &self.fonts, resolution);
+            if height <= region.height || size <= region.size / 2.0 {
+                return text;
+            }
+            size *= 0.9;
+        }
+    }
+
+    fn draw_icon(&self, ctx: &mut Context, icon: &str, x: f32, y: f32, size: f32) -> GameResult {
+        let icon = icon_index(icon);
         let col = icon % 8;
         let row = icon / 8;
-        ggez::graphics::draw(ctx, self.icons, ggez::graphics::DrawParam::new()
+        let icon_size = f32::from(self.icons.width()) / 8.0;
+        ggez::graphics::draw(ctx, &self.icons, DrawParam::new()
             .src(Rect {
                 x: col as f32 / 8.0,
                 y: row as f32 / 8.0,
                 w: 1.0 / 8.0,
                 h: 1.0 / 8.0,
             })
-            .dest([(f32::from(CARD_WIDTH) / 2.0 - 80.0), 80.0])
-            .scale([10.0, 10.0]))?; // 160x160
-        let title = Text::new(TextFragment::new(config.title.as_str())
-            .scale(Scale { x: 60.0, y: 60.0 }));
-        let width = title.width(ctx);
-        let x = (f32::from(CARD_WIDTH) - width as f32) / 2.0;
-        ggez::graphics::draw(ctx, &title, ggez::graphics::DrawParam::new()
-            .dest([x, 20.0])
-            .color(ggez::graphics::BLACK))?;
-        let mut y = 70.0 + 160.0 + 10.0;
-        for line in &config.description {
-            let line = Text::new(TextFragment::new(line.as_str())
-                .scale(Scale { x: 50.0, y: 50.0 }));
-            let width = line.width(ctx);
-            let x = (f32::from(CARD_WIDTH) - width as f32) / 2.0;
-            ggez::graphics::draw(ctx, &line, ggez::graphics::DrawParam::new()
-                .dest([x, y])
-                .color(ggez::graphics::BLACK))?;
-            y += line.height(ctx) as f32;
-        }
-        ggez::graphics::present(ctx)?;
-        ggez::graphics::set_canvas(ctx, None);
-        unwrap_canvas(ctx, card_render)
+            .dest([x, y])
+            .scale([size / icon_size, size / icon_size]))
     }
 }
 
-fn render_card_back(ctx: &mut Context, base: &Image, back: &Image) -> GameResult<Image> {
-    let card_render = ggez::graphics::Canvas::new(
-        ctx,
-        CARD_WIDTH,
-        CARD_HEIGHT,
-        ggez::conf::NumSamples::One,
-    )?;
-    ggez::graphics::set_canvas(ctx, Some(&card_render));
-    ggez::graphics::set_screen_coordinates(ctx, Rect {
-        x: 0.0,
-        y: 0.0,
-        w: f32::from(CARD_WIDTH),
-        h: f32::from(CARD_HEIGHT),
-    })?;
-    ggez::graphics::draw(ctx, base, ggez::graphics::DrawParam::new()
-        .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 })
-        .dest([0.0, 0.0]))?;
-    ggez::graphics::draw(ctx, back, ggez::graphics::DrawParam::new()
-        .src(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 })
-        .dest([0.0, 0.0]))?;
-    ggez::graphics::present(ctx)?;
-    ggez::graphics::set_canvas(ctx, None);
-    unwrap_canvas(ctx, card_render)
+/// Trap and boss cards look like their deck, other cards like their kind
+/// of effect.
+fn default_frame(id: &str, effect: &config::CardEffect, decks: &config::Decks) -> &'static str {
+    if decks.boss == id {
+        return "boss";
+    }
+    if decks.trap.contains_key(id) {
+        return "trap";
+    }
+    match effect {
+        config::CardEffect::Enemy { .. } => "enemy",
+        config::CardEffect::Buy { .. } => "buy",
+        config::CardEffect::Buff(_) => "buff",
+        // Boss buffs are only ever dealt as traps.
+        config::CardEffect::BossBuff(_) => "trap",
+        _ => "default",
+    }
 }
 
-fn unwrap_canvas(ctx: &mut Context, canvas: Canvas) -> GameResult<Image> {
-    let image = canvas.into_inner();
-    let mut data = image.to_rgba8(ctx)?;
-    let (first, second) = data.split_at_mut(usize::from(image.width()) * usize::from(image.height()) * 2);
-    let first = first.chunks_mut(usize::from(image.width() * 4));
-    let second = second.chunks_mut(usize::from(image.width() * 4));
-    for (a, b) in first.zip(second.rev()) {
-        let mut c = [0; (CARD_WIDTH * 4) as usize];
-        c.copy_from_slice(a);
-        a.copy_from_slice(b);
-        b.copy_from_slice(&c);
+fn stat(effect: &config::CardEffect, stat: config::Stat) -> Option<u32> {
+    match (stat, effect) {
+        (config::Stat::Attack, config::CardEffect::Enemy { attack, .. }) => Some(*attack),
+        (config::Stat::Health, config::CardEffect::Enemy { health, .. }) => Some(*health),
+        (config::Stat::Price, config::CardEffect::Buy { price, .. }) => Some(*price),
+        _ => None,
     }
-    let image = Image::from_rgba8(ctx, image.width(), image.height(), &data)?;
-    Ok(image)
 }
 
 fn make_transparent(ctx: &mut Context, image: &Image) -> GameResult<Image> {
     let mut pixels = image.to_rgba8(ctx)?;
     for pixel in pixels.chunks_mut(4) {
-        if pixel == &[163, 73, 164, 255] || pixel == &[200, 191, 231, 255] {
+        if pixel == [163, 73, 164, 255] || pixel == [200, 191, 231, 255] {
             pixel[0] = 0;
             pixel[1] = 0;
             pixel[2] = 0;
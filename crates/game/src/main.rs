@@ -1,47 +1,93 @@
 #![allow(unused)]
 #![warn(unused_must_use)]
 
+pub mod bindings;
 pub mod card;
+#[cfg(feature = "dev")]
+mod dev;
 pub mod loader;
+pub mod options;
+pub mod save;
+pub mod sounds;
 pub mod views;
 
 use std::convert::TryInto;
 use engine::{Ctx, FrameRenderer, Icon, Renderer, Result, Texture, SCREEN_HEIGHT, SCREEN_WIDTH};
 use engine::ggez::{self, graphics::{Align, Text, TextFragment, Scale}};
 use card::Decks;
+use options::Options;
 
 pub struct GameData {
     decks: Decks,
+    options: Options,
 }
 
 struct TestGame {
     renderer: Renderer,
     data: GameData,
     view_stack: crate::views::ViewStack,
+    options_applied: bool,
+    #[cfg(feature = "dev")]
+    dev: dev::DevTools,
 }
 
 const CARD_WIDTH: f32 = 320.0;
 const CARD_HEIGHT: f32 = 448.0;
 
 impl engine::Game for TestGame {
-    fn update(&mut self, ctx: &mut Ctx<'_>) -> Result {
-        self.view_stack.update(&self.data, ctx, 1.0 / 60.0)
+    fn update(&mut self, ctx: &mut Ctx<'_>, dt: f32) -> Result {
+        if !self.options_applied {
+            self.options_applied = true;
+            self.data.options.apply(ctx);
+        }
+        self.renderer.update(ctx)?;
+        #[cfg(feature = "dev")]
+        {
+            if self.dev.update(&self.data, ctx, &mut self.view_stack) {
+                return Ok(());
+            }
+        }
+        self.view_stack.update(&mut self.data, ctx, dt)
     }
 
     fn draw(&mut self, ctx: &mut Ctx<'_>) -> Result {
         // let (x, y) = ctx.mouse_position();
-        let mut renderer = self.renderer.frame(ctx);
+        let mut renderer = self.renderer.frame(ctx)?;
         // let card = self.data.decks.draw.iter().next().unwrap().texture;
         // renderer.draw_icon(Icon::new(0), 10.0, 10.0, CARD_WIDTH, CARD_HEIGHT)?;
         // renderer.draw(card, 10.0, 10.0, CARD_WIDTH, CARD_HEIGHT)?;
         self.view_stack.draw(&mut renderer)?;
+        #[cfg(feature = "dev")]
+        self.dev.draw(&mut renderer, &mut self.view_stack)?;
         // ggez::graphics::draw_queued_text(ctx, ggez::graphics::DrawParam::default(), None, ggez::graphics::FilterMode::Linear)?;
         Ok(())
     }
 }
 
 fn main() {
-    let result = engine::run(&|ctx| {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("--export-cards") => {
+            let dir = args.next().unwrap_or_else(|| "export".to_owned());
+            let result = engine::run_tool(&|ctx| {
+                loader::export::export_cards(ctx, std::path::Path::new(&dir))
+            });
+            if let Err(e) = result {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(arg) => {
+            eprintln!("unknown argument: {}", arg);
+            eprintln!("usage: game [--export-cards <dir>]");
+            std::process::exit(1);
+        }
+        None => {}
+    }
+
+    let options = Options::load();
+    let result = engine::run(options.window(), &|ctx| {
         let resources = match loader::load_resources(ctx) {
             Ok(resources) => {
                 println!("loaded resources");
@@ -59,8 +105,14 @@ fn main() {
         }).expect("failed to set screen coordintes");
         Ok(Box::new(TestGame {
             renderer: resources.renderer,
-            data: GameData { decks: resources.decks.clone() },
-            view_stack: views::ViewStack::new(views::MenuView::new(views::main::MainMenu)),
+            data: GameData {
+                decks: resources.decks.clone(),
+                options: options.clone(),
+            },
+            view_stack: views::ViewStack::new(views::MenuView::new(views::main::MainMenu::new(ctx))),
+            options_applied: false,
+            #[cfg(feature = "dev")]
+            dev: dev::DevTools::new(),
         }))
     });
     if let Err(e) = result {
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use engine::{Ctx, Result, WindowMode, WindowSettings};
+use engine::ggez::GameError;
+use crate::bindings::KeyBindings;
+
+const OPTIONS_FILE: &str = "settings.ron";
+
+/// What it takes to send cards from the hand back into the deck and draw
+/// replacements.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub enum Mulligan {
+    #[default]
+    OncePerLevel,
+    Coins(u32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Options {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub window_mode: WindowMode,
+    pub monitor: Option<String>,
+    pub game_speed: f32,
+    pub key_bindings: KeyBindings,
+    pub mulligan: Mulligan,
+    pub show_fps: bool,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        let window = WindowSettings::default();
+        Options {
+            window_width: window.width,
+            window_height: window.height,
+            window_mode: window.mode,
+            monitor: window.monitor,
+            game_speed: 1.0,
+            key_bindings: KeyBindings::default(),
+            mulligan: Mulligan::default(),
+            show_fps: false,
+            music_volume: 0.7,
+            effects_volume: 0.8,
+        }
+    }
+}
+
+fn options_path() -> Option<PathBuf> {
+    engine::user_config_dir().map(|dir| dir.join(OPTIONS_FILE))
+}
+
+impl Options {
+    pub fn load() -> Options {
+        let path = match options_path() {
+            Some(path) if path.is_file() => path,
+            _ => return Options::default(),
+        };
+        let options = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|options| ron::from_str(&options).map_err(|e| e.to_string()));
+        match options {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("could not load settings from {}: {}", path.display(), e);
+                Options::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result {
+        let path = options_path().ok_or_else(|| GameError::FilesystemError(
+            "could not find config directory".to_owned()
+        ))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let options = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| GameError::FilesystemError(
+                format!("could not serialize settings: {}", e)
+            ))?;
+        std::fs::write(path, options)?;
+        Ok(())
+    }
+
+    /// Applies everything but the window settings, which go to
+    /// `engine::run` instead. Settings that change later are applied by the
+    /// settings menu as they change.
+    pub fn apply(&self, ctx: &mut Ctx<'_>) {
+        ctx.set_debug_overlay(self.show_fps);
+        ctx.set_input_map(self.key_bindings.input_map());
+        let mut audio = ctx.audio();
+        audio.set_music_volume(self.music_volume);
+        audio.set_effects_volume(self.effects_volume);
+    }
+
+    pub fn window(&self) -> WindowSettings {
+        WindowSettings {
+            width: self.window_width,
+            height: self.window_height,
+            mode: self.window_mode,
+            monitor: self.monitor.clone(),
+        }
+    }
+}
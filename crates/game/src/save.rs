@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use engine::{Icon, Result};
+use engine::ggez::{self, Context, GameError};
+use crate::card;
+
+const SAVE_FILE: &str = "save.ron";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunSave {
+    pub level: usize,
+    pub player: Player,
+    pub coins: u32,
+    pub run_deck: Vec<String>,
+    pub deck: Vec<String>,
+    pub discards: Vec<String>,
+    pub trap_deck: Vec<String>,
+    pub trap_discards: Vec<String>,
+    pub hand: Vec<String>,
+    pub cells: Vec<Option<String>>,
+    pub boss_bonuses: Vec<String>,
+    #[serde(default)]
+    pub mulligan_used: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Player {
+    pub health: u32,
+    pub max_health: Option<u32>,
+    pub attack: u32,
+    pub weapon: Option<Weapon>,
+    pub buffs: Vec<Buff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Weapon {
+    pub icon: u32,
+    pub damage: u32,
+    pub durability: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Buff {
+    pub icon: u32,
+    pub kind: BuffKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum BuffKind {
+    NextAttackBonus { damage: u32 },
+    AttackBonus { damage: u32 },
+}
+
+impl Player {
+    pub fn from_creature(creature: &card::Creature) -> Player {
+        Player {
+            health: creature.health,
+            max_health: creature.max_health,
+            attack: creature.attack,
+            weapon: creature.weapon.as_ref().map(|weapon| Weapon {
+                icon: weapon.icon.index(),
+                damage: weapon.damage,
+                durability: weapon.durability,
+            }),
+            buffs: creature.buffs.iter().map(|buff| Buff {
+                icon: buff.icon.index(),
+                kind: match buff.kind {
+                    card::BuffKind::NextAttackBonus { damage } => BuffKind::NextAttackBonus { damage },
+                    card::BuffKind::AttackBonus { damage } => BuffKind::AttackBonus { damage },
+                },
+            }).collect(),
+        }
+    }
+
+    pub fn into_creature(self) -> card::Creature {
+        card::Creature {
+            icon: Icon::FIGHTER,
+            health: self.health,
+            max_health: self.max_health,
+            attack: self.attack,
+            rewards: Vec::new(),
+            weapon: self.weapon.map(|weapon| card::Weapon {
+                icon: Icon::new(weapon.icon),
+                damage: weapon.damage,
+                durability: weapon.durability,
+            }),
+            buffs: self.buffs.into_iter().map(|buff| card::Buff {
+                icon: Icon::new(buff.icon),
+                kind: match buff.kind {
+                    BuffKind::NextAttackBonus { damage } => card::BuffKind::NextAttackBonus { damage },
+                    BuffKind::AttackBonus { damage } => card::BuffKind::AttackBonus { damage },
+                },
+            }).collect(),
+        }
+    }
+}
+
+fn save_path(ctx: &Context) -> PathBuf {
+    ggez::filesystem::user_data_dir(ctx).join(SAVE_FILE)
+}
+
+pub fn exists(ctx: &Context) -> bool {
+    save_path(ctx).is_file()
+}
+
+pub fn load(ctx: &Context) -> Result<Option<RunSave>> {
+    let path = save_path(ctx);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let save = std::fs::read_to_string(path)?;
+    let save = ron::from_str(&save)
+        .map_err(|e| GameError::ResourceLoadError(
+            format!("could not deserialize save: {}", e)
+        ))?;
+    Ok(Some(save))
+}
+
+pub fn store(ctx: &Context, save: &RunSave) -> Result {
+    let path = save_path(ctx);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let save = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())
+        .map_err(|e| GameError::FilesystemError(
+            format!("could not serialize save: {}", e)
+        ))?;
+    std::fs::write(path, save)?;
+    Ok(())
+}
+
+pub fn clear(ctx: &Context) -> Result {
+    let path = save_path(ctx);
+    if path.is_file() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,13 @@
+//! Names of the sounds and music tracks listed in `resources/sfx/sounds.ron`.
+
+pub const CARD_PICKUP: &str = "card_pickup";
+pub const CARD_DROP: &str = "card_drop";
+pub const HIT: &str = "hit";
+pub const HURT: &str = "hurt";
+pub const COIN: &str = "coin";
+pub const TRAP: &str = "trap";
+pub const BOSS_BUFF: &str = "boss_buff";
+pub const DEATH: &str = "death";
+
+pub const MENU_MUSIC: &str = "menu";
+pub const GAME_MUSIC: &str = "game";
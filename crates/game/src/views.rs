@@ -3,9 +3,12 @@ pub mod settings;
 pub mod game;
 pub mod card_list;
 pub mod card_select;
+pub mod pause;
+mod tooltip;
 
-use engine::{ggez, Ctx, FrameRenderer, Result};
-use engine::ggez::graphics::{DrawParam, Scale, Text, TextFragment};
+use engine::{ggez, Action, Ctx, FrameRenderer, Result, ScreenText, SCREEN_HEIGHT, SCREEN_WIDTH};
+use engine::ggez::graphics::DrawParam;
+use engine::tween::{Ease, Tween};
 use crate::GameData;
 
 pub use self::main::MainMenu;
@@ -13,6 +16,7 @@ pub use self::settings::Settings;
 pub use self::game::GameState;
 pub use self::card_list::CardList;
 pub use self::card_select::CardSelect;
+pub use self::pause::Pause;
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum DrawKind {
@@ -25,16 +29,60 @@ pub enum ViewChange {
     Push(Box<dyn View>),
     Pop,
     Replace(Box<dyn View>),
+    Reset(Box<dyn View>),
+    /// Makes the change with an animation showing both the old and the new
+    /// views. Views don't get any input until it finishes.
+    Transition(Transition, Box<ViewChange>),
 }
 
+impl ViewChange {
+    pub fn with_transition(self, transition: Transition) -> ViewChange {
+        ViewChange::Transition(transition, Box::new(self))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Transition {
+    /// Fades out to black and then fades in the new views.
+    Fade,
+    /// Slides the new views in from the right, or from the left when
+    /// popping.
+    Slide,
+    /// Fades in the new views over the old ones.
+    Crossfade,
+}
+
+const TRANSITION_TIME: f32 = 0.5;
+
 pub trait View {
     fn draw_kind(&self) -> DrawKind;
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange>;
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange>;
     fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result;
+    /// Music to play while this view is on top. `None` keeps whatever was
+    /// playing before.
+    fn music(&self) -> Option<&'static str> { None }
+
+    /// Name shown in the developer overlay.
+    #[cfg(feature = "dev")]
+    fn name(&self) -> &'static str { std::any::type_name::<Self>() }
+
+    #[cfg(feature = "dev")]
+    fn as_game_state(&mut self) -> Option<&mut GameState> { None }
+}
+
+struct ActiveTransition {
+    kind: Transition,
+    backwards: bool,
+    progress: Tween<f32>,
+    // The old views are the first `kept` views of the stack followed by
+    // the ones that the change removed.
+    kept: usize,
+    removed: Vec<Box<dyn View>>,
 }
 
 pub struct ViewStack {
     views: Vec<Box<dyn View>>,
+    transition: Option<ActiveTransition>,
 }
 
 impl ViewStack {
@@ -43,44 +91,133 @@ impl ViewStack {
             views: vec![
                 Box::new(view),
             ],
+            transition: None,
         }
     }
 
-    pub fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result {
+    pub fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result {
         let view = self.views.last_mut().expect("view stack empty");
+        if let Some(music) = view.music() {
+            ctx.audio().play_music(music);
+        }
+        if let Some(transition) = &mut self.transition {
+            transition.progress.update(dt);
+            if transition.progress.value() >= 1.0 {
+                self.transition = None;
+            }
+            return Ok(());
+        }
         match view.update(data, ctx, dt)? {
-            ViewChange::None => {}
-            ViewChange::Push(view) => self.views.push(view),
-            ViewChange::Pop => {
-                assert!(self.views.len() >= 2, "can't pop last view");
-                self.views.pop();
+            ViewChange::Transition(kind, change) => {
+                let backwards = matches!(*change, ViewChange::Pop);
+                let count = self.views.len();
+                let removed = self.apply(*change);
+                let mut progress = Tween::new(0.0);
+                progress.retarget(1.0, TRANSITION_TIME, Ease::QuadInOut);
+                self.transition = Some(ActiveTransition {
+                    kind,
+                    backwards,
+                    progress,
+                    kept: count - removed.len(),
+                    removed,
+                });
+            }
+            change => {
+                self.apply(change);
             }
-            ViewChange::Replace(new) => *view = new,
         }
         Ok(())
     }
 
-    pub fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
-        self.draw_at(renderer, self.views.len() - 1)
+    /// Names of the views, from the bottom of the stack.
+    #[cfg(feature = "dev")]
+    pub fn view_names(&self) -> Vec<&'static str> {
+        self.views.iter().map(|view| view.name()).collect()
+    }
+
+    /// The topmost game in the stack, even if other views are above it.
+    #[cfg(feature = "dev")]
+    pub fn game_state(&mut self) -> Option<&mut GameState> {
+        self.views.iter_mut().rev().find_map(|view| view.as_game_state())
+    }
+
+    /// Makes the change, returning the views that it removed.
+    fn apply(&mut self, change: ViewChange) -> Vec<Box<dyn View>> {
+        match change {
+            ViewChange::None => Vec::new(),
+            ViewChange::Push(view) => {
+                self.views.push(view);
+                Vec::new()
+            }
+            ViewChange::Pop => {
+                assert!(self.views.len() >= 2, "can't pop last view");
+                self.views.pop().into_iter().collect()
+            }
+            ViewChange::Replace(new) => {
+                let view = self.views.last_mut().expect("view stack empty");
+                vec![std::mem::replace(view, new)]
+            }
+            ViewChange::Reset(new) => std::mem::replace(&mut self.views, vec![new]),
+            // A transition is already running, so this one happens at once.
+            ViewChange::Transition(_, change) => self.apply(*change),
+        }
     }
 
-    fn draw_at(&mut self, renderer: &mut FrameRenderer<'_>, idx: usize) -> Result {
-        match self.views[idx].draw_kind() {
-            DrawKind::Opaque => {}
-            DrawKind::OnTop if idx == 0 => {
-                panic!("first view wants to be drawn on top of nothing");
+    pub fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
+        let transition = match &mut self.transition {
+            Some(transition) => transition,
+            None => return draw_views(renderer, &mut self.views.iter_mut().collect::<Vec<_>>()),
+        };
+        let t = transition.progress.value();
+        let (kind, backwards) = (transition.kind, transition.backwards);
+        let old = &mut self.views[..transition.kept]
+            .iter_mut()
+            .chain(transition.removed.iter_mut())
+            .collect::<Vec<_>>();
+        match kind {
+            Transition::Fade if t < 0.5 => {
+                draw_views(renderer, old)?;
+                renderer.draw_fade(t * 2.0)
+            }
+            Transition::Fade => {
+                draw_views(renderer, &mut self.views.iter_mut().collect::<Vec<_>>())?;
+                renderer.draw_fade(2.0 - t * 2.0)
             }
-            DrawKind::OnTop => {
-                self.draw_at(renderer, idx - 1)?;
+            Transition::Slide => {
+                let direction = if backwards { -1.0 } else { 1.0 };
+                renderer.push_transform(DrawParam::new().dest([-direction * t * SCREEN_WIDTH, 0.0]))?;
+                draw_views(renderer, old)?;
+                renderer.pop_transform()?;
+                renderer.push_transform(DrawParam::new().dest([direction * (1.0 - t) * SCREEN_WIDTH, 0.0]))?;
+                draw_views(renderer, &mut self.views.iter_mut().collect::<Vec<_>>())?;
+                renderer.pop_transform()
+            }
+            Transition::Crossfade => {
+                draw_views(renderer, old)?;
+                renderer.draw_layer(t, |renderer| draw_views(renderer, &mut self.views.iter_mut().collect::<Vec<_>>()))
             }
         }
-        self.views[idx].draw(renderer)
     }
 }
 
+/// Draws the top view, along with the views below it that it is drawn on
+/// top of.
+fn draw_views(renderer: &mut FrameRenderer<'_>, views: &mut [&mut Box<dyn View>]) -> Result {
+    let mut first = views.len() - 1;
+    while views[first].draw_kind() == DrawKind::OnTop {
+        assert!(first > 0, "first view wants to be drawn on top of nothing");
+        first -= 1;
+    }
+    for view in &mut views[first..] {
+        view.draw(renderer)?;
+    }
+    Ok(())
+}
+
 const BUTTON_WIDTH: f32 = 1000.0;
 const BUTTON_HEIGHT: f32 = 80.0;
 const SLIDE_LENGTH: f32 = 30.0;
+const SLIDE_TIME: f32 = 0.1;
 const INDENT_LENGTH: f32 = 100.0;
 
 pub enum ButtonState {
@@ -89,26 +226,25 @@ pub enum ButtonState {
 }
 
 struct Button<I> {
-    text: Text,
+    text: ScreenText,
     spec: ButtonSpec<I>,
-    slide: f32,
+    slide: Tween<f32>,
     hover: bool,
 }
 
 impl<I> Button<I> {
     fn from_spec(spec: ButtonSpec<I>) -> Self {
-        let fragment = TextFragment::new(spec.text).scale(Scale::uniform(70.0));
-        let text = Text::new(fragment);
+        let text = ScreenText::new(spec.text.as_str(), 70.0);
         Button {
             text,
             spec,
-            slide: 0.0,
+            slide: Tween::new(0.0),
             hover: false,
         }
     }
 
-    fn draw(&self, renderer: &mut engine::FrameRenderer<'_>, x: f32, y: f32) -> Result {
-        let x = x + self.slide * SLIDE_LENGTH;
+    fn draw(&mut self, renderer: &mut engine::FrameRenderer<'_>, x: f32, y: f32) -> Result {
+        let x = x + self.slide.value();
         let (x, y) = renderer.ctx().round_to_screen((x, y));
         let textures = renderer.textures();
         let texture = match self.spec.state {
@@ -117,16 +253,13 @@ impl<I> Button<I> {
             ButtonState::Selected => textures.button_selected,
         };
         renderer.draw(texture, x, y, BUTTON_WIDTH, BUTTON_HEIGHT)?;
-        // ggez::graphics::draw(renderer.ggez(), &self.text, DrawParam::new()
-        //     .dest([x + 10.0, y + 5.0])
-        //     .color(ggez::graphics::BLACK))
-        ggez::graphics::queue_text(renderer.ggez(), &self.text, [x + 10.0, y + 5.0], Some(ggez::graphics::BLACK));
+        renderer.queue_text(&mut self.text, x + 10.0, y + 5.0, ggez::graphics::BLACK)?;
         Ok(())
     }
 }
 
 pub struct ButtonSpec<I> {
-    pub text: &'static str,
+    pub text: String,
     pub state: ButtonState,
     pub on_click: I,
     pub indent_level: u32,
@@ -134,7 +267,7 @@ pub struct ButtonSpec<I> {
 
 impl<I> ButtonSpec<I> {
     fn matches(&self, other: &Self) -> bool {
-        (self.text, self.indent_level) == (other.text, other.indent_level)
+        (&self.text, self.indent_level) == (&other.text, other.indent_level)
     }
 }
 
@@ -142,7 +275,16 @@ pub trait MenuSpec: Sized {
     type Input: Clone;
 
     fn top_padding() -> f32 { 0.0 }
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, action: Self::Input) -> Result<ViewChange>;
+    fn draw_kind() -> DrawKind { DrawKind::Opaque }
+    fn music() -> Option<&'static str> { None }
+    fn back(&self) -> Option<Self::Input> { None }
+    /// Checked every frame before any button, so a spec can react to input
+    /// that is not a button press.
+    fn poll(&mut self, ctx: &Ctx<'_>) -> Option<Self::Input> { None }
+    /// Input for left or right on the focused button, for buttons that
+    /// adjust a value like a slider.
+    fn step(&self, input: &Self::Input, forward: bool) -> Option<Self::Input> { None }
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, action: Self::Input) -> Result<ViewChange>;
     fn create_elements(&self) -> Vec<ButtonSpec<Self::Input>>;
 }
 
@@ -175,6 +317,9 @@ fn merge_buttons<I>(mut old: Vec<Button<I>>, mut new: Vec<ButtonSpec<I>>) -> Vec
 pub struct MenuView<S: MenuSpec> {
     spec: S,
     current_buttons: Vec<Button<S::Input>>,
+    scroll: f32,
+    last_scroll: Option<f32>,
+    focus: Option<usize>,
 }
 
 impl<S: MenuSpec> MenuView<S> {
@@ -183,10 +328,46 @@ impl<S: MenuSpec> MenuView<S> {
         MenuView {
             spec,
             current_buttons,
+            scroll: 0.0,
+            last_scroll: None,
+            focus: None,
         }
     }
+
+    fn update_scroll(&mut self, ctx: &Ctx<'_>) {
+        let scroll = ctx.scroll_position().1 * 100.0;
+        let delta = scroll - self.last_scroll.unwrap_or(scroll);
+        self.last_scroll = Some(scroll);
+        let height = 80.0 * 2.0 + S::top_padding() + self.current_buttons.len() as f32 * BUTTON_HEIGHT * 1.4;
+        let scroll_cap = (height - SCREEN_HEIGHT).max(0.0);
+        self.scroll += delta;
+        if let Some(focus) = self.focus {
+            let y = 80.0 + S::top_padding() + focus as f32 * BUTTON_HEIGHT * 1.4;
+            self.scroll = self.scroll
+                .max(y + BUTTON_HEIGHT + 80.0 - SCREEN_HEIGHT)
+                .min(y - 80.0);
+        }
+        self.scroll = self.scroll.clamp(0.0, scroll_cap);
+    }
+
+    fn update_focus(&mut self, ctx: &Ctx<'_>) {
+        let count = self.current_buttons.len();
+        if ctx.is_mouse_moved() || count == 0 {
+            self.focus = None;
+        }
+        if count == 0 {
+            return;
+        }
+        if ctx.is_action_click(Action::Down) {
+            self.focus = Some(self.focus.map_or(0, |f| (f + 1) % count));
+        }
+        if ctx.is_action_click(Action::Up) {
+            self.focus = Some(self.focus.map_or(count - 1, |f| (f + count - 1) % count));
+        }
+        self.focus = self.focus.map(|f| f.min(count - 1));
+    }
     
-    fn update_spec(&mut self, data: &GameData, ctx: &mut Ctx<'_>, input: S::Input) -> Result<ViewChange> {
+    fn update_spec(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, input: S::Input) -> Result<ViewChange> {
         let change = self.spec.update(data, ctx, input)?;
         let new_buttons = self.spec.create_elements();
         self.current_buttons = merge_buttons(
@@ -198,7 +379,7 @@ impl<S: MenuSpec> MenuView<S> {
 
     fn buttons_with_positions(&mut self) -> impl Iterator<Item = (&mut Button<S::Input>, f32, f32)> + '_ {
         let x = 80.0;
-        let mut y = 80.0 + S::top_padding();
+        let mut y = 80.0 + S::top_padding() - self.scroll;
         self.current_buttons.iter_mut()
             .map(move |b| {
                 let x = x + INDENT_LENGTH * b.spec.indent_level as f32;
@@ -211,28 +392,59 @@ impl<S: MenuSpec> MenuView<S> {
 
 impl<S: MenuSpec> View for MenuView<S> {
     fn draw_kind(&self) -> DrawKind {
-        DrawKind::Opaque
+        S::draw_kind()
+    }
+
+    fn music(&self) -> Option<&'static str> {
+        S::music()
     }
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+        if let Some(input) = self.spec.poll(ctx) {
+            return self.update_spec(data, ctx, input);
+        }
+        if ctx.is_action_click(Action::Back) {
+            if let Some(input) = self.spec.back() {
+                return self.update_spec(data, ctx, input);
+            }
+        }
+        self.update_focus(ctx);
+        self.update_scroll(ctx);
+        let forward = ctx.is_action_click(Action::Right);
+        if forward || ctx.is_action_click(Action::Left) {
+            let target = self.focus.or_else(|| self.current_buttons.iter().position(|b| b.hover));
+            let input = target.and_then(|index| self.spec.step(&self.current_buttons[index].spec.on_click, forward));
+            if let Some(input) = input {
+                return self.update_spec(data, ctx, input);
+            }
+        }
         for button in &mut self.current_buttons {
-            let delta_slide = if button.hover { 1.0 } else { -1.0 };
-            let slide = (button.slide + delta_slide * dt * 10.0);
-            let slide = if slide < 0.0 { 0.0 } else if slide > 1.0 { 1.0 } else { slide };
-            button.slide = slide;
+            let slide = if button.hover { SLIDE_LENGTH } else { 0.0 };
+            if button.slide.target() != slide {
+                button.slide.retarget(slide, SLIDE_TIME, Ease::QuadOut);
+            }
+            button.slide.update(dt);
         }
         let (mouse_x, mouse_y) = ctx.mouse_position();
+        let focus = self.focus;
         let mut input = None;
-        for (button, x, y) in self.buttons_with_positions() {
+        for (index, (button, x, y)) in self.buttons_with_positions().enumerate() {
             let inside = mouse_x >= x &&
                 mouse_y >= y &&
                 mouse_x < x + BUTTON_WIDTH &&
                 mouse_y < y + BUTTON_HEIGHT;
-            button.hover = inside;
+            button.hover = match focus {
+                Some(focus) => focus == index,
+                None => inside,
+            };
             if inside && ctx.is_mouse_click() {
                 input = Some(button.spec.on_click.clone());
                 break;
             }
+            if focus == Some(index) && ctx.is_action_click(Action::Confirm) {
+                input = Some(button.spec.on_click.clone());
+                break;
+            }
         }
         if let Some(input) = input {
             self.update_spec(data, ctx, input)
@@ -242,10 +454,13 @@ impl<S: MenuSpec> View for MenuView<S> {
     }
 
     fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
+        if S::draw_kind() == DrawKind::OnTop {
+            renderer.draw_fade(0.8)?;
+        }
         for (button, x, y) in self.buttons_with_positions() {
             button.draw(renderer, x, y)?;
         }
-        ggez::graphics::draw_queued_text(renderer.ggez(), DrawParam::default(), None, ggez::graphics::FilterMode::Linear)?;
+        renderer.draw_queued_text()?;
         Ok(())
     }    
 }
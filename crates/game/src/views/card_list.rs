@@ -1,6 +1,7 @@
-use engine::{Ctx, FrameRenderer, Result};
+use engine::{Action, Ctx, FrameRenderer, Result};
+use engine::ggez::graphics::Rect;
 use crate::{GameData, card::Card};
-use super::{DrawKind, View, ViewChange};
+use super::{tooltip, DrawKind, View, ViewChange};
 
 const CARD_WIDTH: f32 = 320.0 / 1.5;
 const CARD_HEIGHT: f32 = 448.0 / 1.5;
@@ -10,6 +11,7 @@ pub struct CardList {
     start_scroll: Option<f32>,
     scroll_cap: f32,
     y: f32,
+    hover: Option<usize>,
 }
 
 impl CardList {
@@ -19,7 +21,7 @@ impl CardList {
     }
 
     pub fn new_unsorted(cards: Vec<Card>) -> CardList {
-        let rows = (cards.len() + 5) / 6;
+        let rows = cards.len().div_ceil(6);
         let mut scroll_cap = rows as f32 * (CARD_HEIGHT + CARD_WIDTH * 0.2) - CARD_HEIGHT * 2.0;
         if scroll_cap < 0.0 {
             scroll_cap = 0.0;
@@ -29,6 +31,20 @@ impl CardList {
             start_scroll: None,
             y: 0.0,
             scroll_cap,
+            hover: None,
+        }
+    }
+
+    fn card_rect(&self, index: usize) -> Rect {
+        let row = index / 6;
+        let col = index % 6;
+        let x = (col as f32 - 2.5) * CARD_WIDTH * 1.2 + 800.0;
+        let y = (row as f32) * (CARD_HEIGHT + CARD_WIDTH * 0.2) + CARD_HEIGHT / 2.0 + 100.0;
+        Rect {
+            x: x - CARD_WIDTH / 2.0,
+            y: y - CARD_HEIGHT / 2.0 - self.y,
+            w: CARD_WIDTH,
+            h: CARD_HEIGHT,
         }
     }
 }
@@ -38,7 +54,7 @@ impl View for CardList {
         DrawKind::OnTop
     }
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
         let scroll = ctx.scroll_position().1 * 100.0;
         self.y = match self.start_scroll {
             None => {
@@ -55,7 +71,13 @@ impl View for CardList {
             }
             Some(s) => scroll - s,
         };
-        Ok(if ctx.is_mouse_click() {
+        let (mouse_x, mouse_y) = ctx.mouse_position();
+        self.hover = (0..self.cards.len())
+            .find(|&i| self.card_rect(i).contains([mouse_x, mouse_y]));
+        let close = ctx.is_mouse_click() ||
+            ctx.is_action_click(Action::Back) ||
+            ctx.is_action_click(Action::Confirm);
+        Ok(if close {
             ViewChange::Pop
         } else {
             ViewChange::None
@@ -65,17 +87,12 @@ impl View for CardList {
     fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
         renderer.draw_fade(0.8)?;
         for (i, card) in self.cards.iter().enumerate() {
-            let row = i / 6;
-            let col = i % 6;
-            let x = (col as f32 - 2.5) * CARD_WIDTH * 1.2 + 800.0;
-            let y = (row as f32) * (CARD_HEIGHT + CARD_WIDTH * 0.2) + CARD_HEIGHT / 2.0 + 100.0;
-            renderer.draw(
-                card.texture,
-                x - CARD_WIDTH / 2.0,
-                y - CARD_HEIGHT / 2.0 - self.y,
-                CARD_WIDTH,
-                CARD_HEIGHT,
-            )?;
+            let rect = self.card_rect(i);
+            renderer.draw(card.texture, rect.x, rect.y, rect.w, rect.h)?;
+        }
+        if let Some(index) = self.hover {
+            let card = &self.cards[index];
+            tooltip::draw(renderer, self.card_rect(index), Some(card), &tooltip::describe_card(card))?;
         }
         Ok(())
     }
@@ -1,6 +1,6 @@
-use engine::{Ctx, FrameRenderer, Result, ggez::graphics::Text};
+use engine::{Action, Ctx, FrameRenderer, Result, ggez::graphics::Text};
 use crate::{Decks, GameData, card::Card};
-use super::{DrawKind, View, ViewChange};
+use super::{DrawKind, Transition, View, ViewChange};
 
 const CARD_WIDTH: f32 = 320.0;
 const CARD_HEIGHT: f32 = 448.0;
@@ -23,6 +23,7 @@ fn select_treasure(cards: &[Card]) -> Vec<Card> {
 pub struct CardSelect {
     cards: Vec<(Card, f32)>,
     decks: Decks,
+    focus: Option<usize>,
 }
 
 impl CardSelect {
@@ -31,6 +32,7 @@ impl CardSelect {
         CardSelect {
             cards: cards.into_iter().map(|c| (c, 0.0)).collect(),
             decks,
+            focus: None,
         }
     }
 
@@ -38,11 +40,26 @@ impl CardSelect {
         CardSelect {
             cards: cards.into_iter().map(|c| (c, 0.0)).collect(),
             decks,
+            focus: None,
         }
     }
 }
 
 impl CardSelect {
+    fn update_focus(&mut self, ctx: &Ctx<'_>) {
+        let count = self.cards.len();
+        if ctx.is_mouse_moved() || count == 0 {
+            self.focus = None;
+            return;
+        }
+        if ctx.is_action_click(Action::Right) || ctx.is_action_click(Action::NextCard) {
+            self.focus = Some(self.focus.map_or(0, |f| (f + 1) % count));
+        }
+        if ctx.is_action_click(Action::Left) || ctx.is_action_click(Action::PreviousCard) {
+            self.focus = Some(self.focus.map_or(count - 1, |f| (f + count - 1) % count));
+        }
+    }
+
     fn card_positions(&self) -> impl Iterator<Item = (f32, f32)> + 'static {
         let center = (self.cards.len().saturating_sub(1) as f32) / 2.0;
         (0..self.cards.len()).map(move |idx| ((idx as f32 - center) * CARD_WIDTH * 1.3 + 800.0, 500.0))
@@ -54,27 +71,31 @@ impl View for CardSelect {
         DrawKind::Opaque
     }
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+        self.update_focus(ctx);
         let (x, y) = ctx.mouse_position();
         let click = ctx.is_mouse_click();
-        for ((cx, cy), (card, dy)) in self.card_positions().zip(self.cards.iter_mut()) {
+        let confirm = ctx.is_action_click(Action::Confirm);
+        let focus = self.focus;
+        for (index, ((cx, cy), (card, dy))) in self.card_positions().zip(self.cards.iter_mut()).enumerate() {
             let inside = x >= cx - CARD_WIDTH / 2.0 &&
                 x <= cx + CARD_WIDTH / 2.0 &&
                 y >= cy - CARD_HEIGHT / 2.0 &&
                 y <= cy + CARD_HEIGHT / 2.0;
-            if inside {
+            let highlighted = match focus {
+                Some(focus) => focus == index,
+                None => inside,
+            };
+            if highlighted {
                 *dy += dt * 10.0;
             } else {
                 *dy -= dt * 10.0;
             }
-            if *dy < 0.0 {
-                *dy = 0.0;
-            } else if *dy > 1.0 {
-                *dy = 1.0;
-            }
-            if inside && click {
+            *dy = dy.clamp(0.0, 1.0);
+            if (inside && click) || (focus == Some(index) && confirm) {
                 self.decks.draw.push(card.clone());
-                return Ok(ViewChange::Replace(Box::new(super::GameState::new(&self.decks))));
+                let state = super::GameState::new(&self.decks);
+                return Ok(ViewChange::Replace(Box::new(state)).with_transition(Transition::Fade));
             }
         }
         Ok(ViewChange::None)
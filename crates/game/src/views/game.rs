@@ -1,13 +1,57 @@
 use std::convert::TryInto;
-use engine::{Ctx, FrameRenderer, Icon, Result, SCREEN_HEIGHT, SCREEN_WIDTH};
-use engine::ggez::graphics::{Text, TextFragment, Scale};
+use engine::{Action, Burst, Camera, Ctx, Effects, FrameRenderer, Icon, Result, ScreenText, SCREEN_HEIGHT, SCREEN_WIDTH};
+use engine::ggez::GameError;
+use engine::tween::{Animation, Ease, Lerp, Parallel, Sequence, Tween};
+use engine::ggez::graphics::Color;
 use crate::GameData;
+use crate::options::Mulligan;
+use crate::sounds;
 use crate::card::{BuffKind, Card, CardEffect, Creature, Decks};
-use crate::views::{DrawKind, View, ViewChange};
-use super::CardList;
+use crate::save::{self, RunSave};
+use crate::views::{DrawKind, MenuView, Transition, View, ViewChange};
+use super::{tooltip, CardList, Pause};
+use crate::bindings::GameAction;
 
 const CARD_WIDTH: f32 = 320.0 / 2.5;
 const CARD_HEIGHT: f32 = 448.0 / 2.5;
+const LEVEL_COUNT: usize = 4;
+const WALK_SPEED: f32 = 3.0;
+const FAST_FORWARD_SPEED: f32 = 4.0;
+const FOCUS_LIFT: f32 = 30.0;
+const BOSS_ZOOM: f32 = 1.4;
+// Hits doing at least this much damage shake the field.
+const HEAVY_HIT: u32 = 5;
+
+const COIN_BURST: Burst = Burst { icon: Icon::COIN, count: 10, speed: 320.0, gravity: 800.0, size: 24.0, lifetime: 0.8 };
+const HEAL_SPARKLE: Burst = Burst { icon: Icon::HEART, count: 8, speed: 90.0, gravity: -160.0, size: 16.0, lifetime: 1.0 };
+const ENEMY_HEAL_SPARKLE: Burst = Burst { icon: Icon::GREEN_HEART, ..HEAL_SPARKLE };
+const TRAP_PUFF: Burst = Burst { icon: Icon::DOT, count: 12, speed: 140.0, gravity: -40.0, size: 48.0, lifetime: 0.6 };
+
+const DAMAGE_COLOR: Color = Color { r: 1.0, g: 0.3, b: 0.3, a: 1.0 };
+const HEAL_COLOR: Color = Color { r: 0.4, g: 1.0, b: 0.4, a: 1.0 };
+const COIN_COLOR: Color = Color { r: 1.0, g: 0.85, b: 0.2, a: 1.0 };
+
+// Where creatures are drawn relative to their cell, and where effects
+// on them are spawned.
+fn player_position(cell: (f32, f32)) -> (f32, f32) {
+    (cell.0 - 32.0 - 8.0, cell.1)
+}
+
+fn enemy_position(cell: (f32, f32)) -> (f32, f32) {
+    (cell.0 + 32.0 + 8.0, cell.1)
+}
+
+fn above(position: (f32, f32)) -> (f32, f32) {
+    (position.0, position.1 - 90.0)
+}
+
+fn hit_shake(damage: u32) -> f32 {
+    if damage >= HEAVY_HIT {
+        (damage as f32 / 10.0).min(1.0)
+    } else {
+        0.0
+    }
+}
 
 struct ActiveCreature {
     creature: Creature,
@@ -73,10 +117,29 @@ impl Rect {
     }
 }
 
+impl From<Rect> for engine::ggez::graphics::Rect {
+    fn from(rect: Rect) -> Self {
+        engine::ggez::graphics::Rect::new(rect.x, rect.y, rect.w, rect.h)
+    }
+}
+
+// How far creatures lunge forward when attacking, sampled at the attack's
+// progress from 0 to 1.
+fn swing() -> Tween<f32> {
+    let distance = 15.0;
+    Tween::new(0.0)
+        .wait(0.23)
+        .to(distance, 0.27, Ease::SineOut)
+        .to(0.0, 0.27, Ease::SineIn)
+}
+
 struct VisibleCard {
     card: Card,
     pos: Rect,
     target_pos: Rect,
+    // Position before the last update, for drawing in between updates.
+    previous: (f32, f32),
+    motion: Tween<(f32, f32)>,
 }
 
 impl VisibleCard {
@@ -85,29 +148,40 @@ impl VisibleCard {
             card,
             pos,
             target_pos: pos,
+            previous: (pos.x, pos.y),
+            motion: Tween::new((pos.x, pos.y)),
         }
     }
 
     fn draw(&self, renderer: &mut FrameRenderer<'_>) -> Result {
+        let (x, y) = self.drawn_center(renderer);
         renderer.draw(
             self.card.texture,
-            self.pos.x - self.pos.w / 2.0,
-            self.pos.y - self.pos.h / 2.0,
+            x - self.pos.w / 2.0,
+            y - self.pos.h / 2.0,
             self.pos.w,
             self.pos.h,
         )
     }
 
     fn draw_back(&self, renderer: &mut FrameRenderer<'_>) -> Result {
+        let (x, y) = self.drawn_center(renderer);
         renderer.draw(
             renderer.textures().card_back,
-            self.pos.x - self.pos.w / 2.0,
-            self.pos.y - self.pos.h / 2.0,
+            x - self.pos.w / 2.0,
+            y - self.pos.h / 2.0,
             self.pos.w,
             self.pos.h,
         )
     }
 
+    /// Where the card is between the last update and the next one, so it
+    /// moves smoothly when drawing faster than updating.
+    fn drawn_center(&self, renderer: &mut FrameRenderer<'_>) -> (f32, f32) {
+        let interpolation = renderer.ctx().interpolation();
+        self.previous.lerp((self.pos.x, self.pos.y), interpolation)
+    }
+
     fn visual_rect(&self) -> Rect {
         Rect {
             x: self.pos.x - self.pos.w / 2.0,
@@ -118,17 +192,24 @@ impl VisibleCard {
     }
 
     fn update(&mut self, dt: f32) {
-        let dx = self.target_pos.x - self.pos.x;
-        let dy = self.target_pos.y - self.pos.y;
-        let dist = (dx * dx + dy * dy).sqrt();
-        let move_by = 400.0 * dt * (1.0 + (dist + 1.0).log(10.0));
-        if dist <= move_by {
-            self.pos = self.target_pos;
-        } else {
-            let (dx, dy) = (dx / dist * move_by, dy / dist * move_by);
-            self.pos.x += dx;
-            self.pos.y += dy;
+        let pos = (self.pos.x, self.pos.y);
+        self.previous = pos;
+        let target = (self.target_pos.x, self.target_pos.y);
+        // Start a new move when the target changes, or when the card was
+        // put somewhere directly, like under the mouse while dragging.
+        if self.motion.target() != target || self.motion.value() != pos {
+            let dx = target.0 - pos.0;
+            let dy = target.1 - pos.1;
+            let dist = (dx * dx + dy * dy).sqrt();
+            let duration = (0.1 + dist / 2500.0).min(0.4);
+            self.motion = Tween::new(pos).to(target, duration, Ease::CubicOut);
         }
+        self.motion.update(dt);
+        let (x, y) = self.motion.value();
+        self.pos.x = x;
+        self.pos.y = y;
+        self.pos.w = self.target_pos.w;
+        self.pos.h = self.target_pos.h;
     }
 
     fn get_creature(&self) -> Option<Creature> {
@@ -156,6 +237,56 @@ impl Cell {
             h: CARD_HEIGHT + 100.0,
         }
     }
+
+    fn card_position(&self) -> (f32, f32) {
+        (self.position.0, self.position.1 + 50.0 + CARD_HEIGHT / 2.0)
+    }
+
+    fn enemy_rect(&self) -> Rect {
+        Rect {
+            x: self.position.0 + 8.0,
+            y: self.position.1 - 68.0,
+            w: 64.0,
+            h: 100.0,
+        }
+    }
+}
+
+/// One step of the preparation history. Cards listed in `removed` went
+/// from cells back to the hand, then the ones in `placed` went from the
+/// hand onto cells.
+struct Placement {
+    removed: Vec<(usize, String)>,
+    placed: Vec<(usize, String)>,
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Inspect {
+    Hand(usize),
+    Cell(usize),
+    Enemy(usize),
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Focus {
+    Hand(usize),
+    Cell(usize),
+}
+
+/// Picks the next option after `current`, wrapping around.
+fn cycle(options: &[usize], current: Option<usize>, forward: bool) -> Option<usize> {
+    if options.is_empty() {
+        return None;
+    }
+    let position = current.and_then(|c| options.iter().position(|&o| o == c));
+    let count = options.len();
+    let next = match position {
+        None if forward => 0,
+        None => count - 1,
+        Some(p) if forward => (p + 1) % count,
+        Some(p) => (p + count - 1) % count,
+    };
+    Some(options[next])
 }
 
 struct Player {
@@ -163,13 +294,35 @@ struct Player {
     cell: usize,
 }
 
+#[derive(Debug)]
 enum ActionState {
     None,
     Finished(f32),
     PlayerMove(f32),
     PlayerAttack(bool, f32),
     EnemyAttack(bool, usize, f32),
-    AcceptBonus(f32, Icon),
+    AcceptBonus(f32),
+}
+
+struct FloatingIcon {
+    icon: Icon,
+    x: f32,
+    y: f32,
+    size: f32,
+    done: bool,
+}
+
+impl FloatingIcon {
+    /// An icon that rises from the given point and shrinks away.
+    fn spawn(icon: Icon, x: f32, y: f32) -> (FloatingIcon, Sequence<FloatingIcon>) {
+        let floating = FloatingIcon { icon, x, y, size: 64.0, done: false };
+        let animation = Sequence::new()
+            .then(Parallel::new()
+                .with(Tween::new(y).to(y - 48.0, 0.6, Ease::CubicOut).animate(|icon: &mut FloatingIcon| &mut icon.y))
+                .with(Tween::new(64.0).wait(0.45).to(0.0, 0.15, Ease::QuadIn).animate(|icon: &mut FloatingIcon| &mut icon.size)))
+            .call(|icon| icon.done = true);
+        (floating, animation)
+    }
 }
 
 struct Field {
@@ -179,6 +332,13 @@ struct Field {
     action: ActionState,
     discards: Vec<Card>,
     boss_bonuses: Vec<Card>,
+    // Sounds for the game to play on its next update.
+    sounds: Vec<&'static str>,
+    // Camera shake for the game to apply on its next update.
+    shake: f32,
+    reward_icons: Vec<(FloatingIcon, Sequence<FloatingIcon>)>,
+    effects: Effects,
+    swing: Tween<f32>,
 }
 
 impl Field {
@@ -228,6 +388,11 @@ impl Field {
             action: ActionState::None,
             discards: Vec::new(),
             boss_bonuses: Vec::new(),
+            sounds: Vec::new(),
+            shake: 0.0,
+            reward_icons: Vec::new(),
+            effects: Effects::new(),
+            swing: swing(),
         }
     }
 
@@ -244,6 +409,10 @@ impl Field {
             }
             CardEffect::Coins { amount } => {
                 self.player_coins += *amount;
+                self.sounds.push(sounds::COIN);
+                let pos = player_position(self.cells[player.cell].position);
+                self.effects.burst(COIN_BURST, pos);
+                self.effects.float_text(&format!("+{}", amount), above(pos), COIN_COLOR);
                 Some(Icon::COIN)
             }
             CardEffect::None => None,
@@ -257,6 +426,9 @@ impl Field {
                 for c in cells {
                     if let Some(creature) = &mut c.enemy {
                         creature.creature.heal(*health);
+                        let pos = enemy_position(c.position);
+                        self.effects.burst(ENEMY_HEAL_SPARKLE, pos);
+                        self.effects.float_text(&format!("+{}", health), above(pos), HEAL_COLOR);
                         return Some(Icon::GREEN_HEART);
                     }
                 }
@@ -268,6 +440,9 @@ impl Field {
             }
             CardEffect::Heal { health } => {
                 player.creature.creature.heal(*health);
+                let pos = player_position(self.cells[player.cell].position);
+                self.effects.burst(HEAL_SPARKLE, pos);
+                self.effects.float_text(&format!("+{}", health), above(pos), HEAL_COLOR);
                 Some(Icon::HEART)
             }
             CardEffect::Attack { use_base, bonus } => {
@@ -276,6 +451,8 @@ impl Field {
                     if let Some(enemy) = &mut c.enemy {
                         let damage = bonus + if *use_base { player.creature.attack_power() } else { 0 };
                         enemy.creature.health = enemy.creature.health.saturating_sub(damage);
+                        let pos = above(enemy_position(c.position));
+                        self.effects.float_text(&format!("-{}", damage), pos, DAMAGE_COLOR);
                         if *use_base {
                             player.creature.spend_attack();
                         }
@@ -301,12 +478,18 @@ impl Field {
                 None
             }
             CardEffect::BossBuff(buff) => {
+                self.sounds.push(sounds::BOSS_BUFF);
                 Some(buff.icon)
             }
         }
     }
 
-    fn update_action(&mut self, dt: f32) {
+    /// Returns whether the action moved on to the next state.
+    fn update_action(&mut self, dt: f32) -> bool {
+        for (icon, animation) in &mut self.reward_icons {
+            animation.update(icon, dt);
+        }
+        self.reward_icons.retain(|(icon, _)| !icon.done);
         let mut action = std::mem::replace(&mut self.action, ActionState::None);
         let new_action = match &mut action {
             ActionState::None => {
@@ -318,6 +501,12 @@ impl Field {
                 if *progress >= 1.0 {
                     let player = self.player.as_mut().unwrap();
                     player.cell += 1;
+                    let cell = &self.cells[player.cell];
+                    let trap_effect = cell.card.as_ref().map(|c| &c.card.effect);
+                    if cell.fixed && !matches!(trap_effect, None | Some(CardEffect::None)) {
+                        self.sounds.push(sounds::TRAP);
+                        self.effects.burst(TRAP_PUFF, cell.position);
+                    }
                     if self.cells[player.cell].enemy.is_some() {
                         Some(ActionState::PlayerAttack(false, 0.0))
                     } else {
@@ -326,7 +515,9 @@ impl Field {
                                 self.boss_bonuses.push(self.cells[player.cell].card.as_ref().map(|c| c.card.clone()).unwrap());
                             }
                             if let Some(icon) = self.apply_effect(&effect) {
-                                Some(ActionState::AcceptBonus(0.0, icon))
+                                let pos = self.cells[self.player.as_ref().unwrap().cell].position;
+                                self.reward_icons.push(FloatingIcon::spawn(icon, pos.0 + 32.0, pos.1));
+                                Some(ActionState::AcceptBonus(0.0))
                             } else {
                                 Some(ActionState::PlayerMove(0.0))
                             }
@@ -343,11 +534,15 @@ impl Field {
                 *progress += dt;
                 if *progress >= 0.5 && !*hit {
                     *hit = true;
+                    let pos = above(enemy_position(self.cells[player.cell].position));
                     let enemy = self.cells[player.cell].enemy.as_mut().unwrap();
                     if enemy.creature.health > 0 {
                         let damage = player.creature.attack_power();
                         enemy.creature.health = enemy.creature.health.saturating_sub(damage);
                         player.creature.spend_attack();
+                        self.sounds.push(sounds::HIT);
+                        self.shake += hit_shake(damage);
+                        self.effects.float_text(&format!("-{}", damage), pos, DAMAGE_COLOR);
                     }
                     if enemy.creature.health == 0 {
                         let rewards = enemy.creature.rewards.clone();
@@ -377,8 +572,13 @@ impl Field {
                     let damage = enemy.attack_power();
                     player.creature.creature.health = player.creature.creature.health.saturating_sub(damage);
                     enemy.spend_attack();
+                    self.sounds.push(sounds::HURT);
+                    self.shake += hit_shake(damage);
+                    let pos = above(player_position(self.cells[player.cell].position));
+                    self.effects.float_text(&format!("-{}", damage), pos, DAMAGE_COLOR);
                     if player.creature.creature.health == 0 {
                         self.player = None;
+                        self.sounds.push(sounds::DEATH);
                     }
                 }
                 if *progress >= 1.0 {
@@ -391,7 +591,7 @@ impl Field {
                     None
                 }
             }
-            ActionState::AcceptBonus(progress, _) => {
+            ActionState::AcceptBonus(progress) => {
                 *progress += dt;
                 if *progress >= 0.6 {
                     Some(ActionState::PlayerMove(0.0))
@@ -400,6 +600,7 @@ impl Field {
                 }
             }
         };
+        let changed = new_action.is_some();
         self.action = new_action.unwrap_or(action);
         if let ActionState::PlayerMove(_) = self.action {
             if let Some(player) = &self.player {
@@ -408,8 +609,10 @@ impl Field {
                 }
             }
         }
+        changed
     }
 
+    /// Draws the field in world coordinates.
     fn render(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
         for (i, cell) in self.cells.iter().enumerate() {
             let icon = if cell.fixed && i > 0 { Icon::RED_CIRCLE } else { Icon::CIRCLE };
@@ -439,11 +642,11 @@ impl Field {
             ActionState::Finished(_) |
             ActionState::PlayerMove(_) |
             ActionState::PlayerAttack(_, _) |
-            ActionState::AcceptBonus(_, _) => {
+            ActionState::AcceptBonus(_) => {
                 for cell in &self.cells {
                     if let Some(enemy) = &cell.enemy {
                         let pos = cell.position;
-                        enemy.draw(pos.0 + 32.0 + 8.0, pos.1, renderer)?;
+                        enemy.draw(enemy_position(pos).0, pos.1, renderer)?;
                     }
                 }
             }
@@ -452,13 +655,10 @@ impl Field {
                     if let Some(enemy) = &cell.enemy {
                         if index != attacker {
                             let pos = cell.position;
-                            enemy.draw(pos.0 + 32.0 + 8.0, pos.1, renderer)?;
+                            enemy.draw(enemy_position(pos).0, pos.1, renderer)?;
                         } else {
                             let pos = cell.position;
-                            let move_by = (progress * std::f32::consts::PI).sin() * 3.0 - 2.0;
-                            let move_by = if move_by < 0.0 { 0.0 } else { move_by };
-                            let swing_distance = 15.0;
-                            let x = pos.0 - move_by * swing_distance;
+                            let x = pos.0 - self.swing.value_at(progress);
                             let y = pos.1;
                             enemy.draw(x + 32.0 + 8.0, y, renderer)?;
                         }
@@ -471,10 +671,10 @@ impl Field {
             ActionState::None |
             ActionState::Finished(_) |
             ActionState::EnemyAttack(_, _, _) |
-            ActionState::AcceptBonus(_, _) => {
+            ActionState::AcceptBonus(_) => {
                 if let Some(player) = &self.player {
                     let pos = self.cells[player.cell].position;
-                    player.creature.draw(pos.0 - 32.0 - 8.0, pos.1, renderer)?;
+                    player.creature.draw(player_position(pos).0, pos.1, renderer)?;
                 }
             }
             ActionState::PlayerMove(progress) => {
@@ -491,30 +691,25 @@ impl Field {
             ActionState::PlayerAttack(_, progress) => {
                 if let Some(player) = &self.player {
                     let pos = self.cells[player.cell].position;
-                    let move_by = (progress * std::f32::consts::PI).sin() * 3.0 - 2.0;
-                    let move_by = if move_by < 0.0 { 0.0 } else { move_by };
-                    let swing_distance = 15.0;
-                    let x = pos.0 - 32.0 + move_by * swing_distance;
+                    let x = pos.0 - 32.0 + self.swing.value_at(progress);
                     let y = pos.1;
                     player.creature.draw(x - 8.0, y, renderer)?;
                 }
             }
         }
 
-        if let ActionState::AcceptBonus(progress, icon) = self.action {
-            let cell = self.player.as_ref().unwrap().cell;
-            let pos = self.cells[cell].position;
-            let x = pos.0 + 32.0;
-            let y = pos.1 - progress * 64.0;
-            renderer.draw_icon(icon, x - 32.0, y - 32.0, 64.0, 64.0)?;
+        for (icon, _) in &self.reward_icons {
+            let size = icon.size;
+            renderer.draw_icon(icon.icon, icon.x - size / 2.0, icon.y - size / 2.0, size, size)?;
         }
+        renderer.draw_effects(&mut self.effects)?;
 
         Ok(())
     }
 }
 
 struct Label {
-    text: Text,
+    text: ScreenText,
     position: (f32, f32),
     get_text: Box<dyn Fn(&GameState) -> String>,
 }
@@ -522,7 +717,7 @@ struct Label {
 impl Label {
     fn new(position: (f32, f32), get_text: impl Fn(&GameState) -> String + 'static) -> Label {
         Label {
-            text: Text::new(TextFragment::new("").scale(Scale::uniform(32.0))),
+            text: ScreenText::rich("", 32.0),
             position,
             get_text: Box::new(get_text),
         }
@@ -554,22 +749,170 @@ pub struct GameState {
     trap_discards: Vec<Card>,
     hand: Vec<VisibleCard>,
     drag: Option<VisibleCard>,
+    drag_from: Option<usize>,
     preparing: bool,
     labels: Vec<Label>,
     buttons: Vec<Button>,
     boss_bonuses: Vec<Card>,
     decks: Decks,
+    checkpoint: Option<RunSave>,
+    step_mode: bool,
+    step_pending: bool,
+    focus: Option<Focus>,
+    selected: Option<VisibleCard>,
+    selected_from: Option<usize>,
+    history: Vec<Placement>,
+    redo_history: Vec<Placement>,
+    mulligan_rule: Mulligan,
+    mulligan_used: bool,
+    // Hand indices picked for a redraw, while picking.
+    mulligan: Option<Vec<usize>>,
+    inspect: Option<Inspect>,
+    // The field is drawn through this, while the hand and the rest of the
+    // interface stay in screen coordinates.
+    camera: Camera,
 }
 
 fn make_deck(cards: &[Card]) -> Vec<Card> {
     use rand::seq::SliceRandom;
-    let mut cards = cards.iter().cloned().collect::<Vec<_>>();
+    let mut cards = cards.to_vec();
     cards.shuffle(&mut rand::thread_rng());
     cards
 }
 
 impl GameState {
     pub fn new(decks: &Decks) -> GameState {
+        let mut state = GameState::with_field(decks, 0, Field::new());
+        state.deck = make_deck(&decks.draw);
+        state.trap_deck = make_deck(&decks.trap);
+        state.layout_cards(true);
+        state.draw_hand();
+        state.draw_traps();
+        state
+    }
+
+    pub fn from_save(data_decks: &Decks, save: RunSave) -> Result<GameState> {
+        if save.level >= LEVEL_COUNT {
+            return Err(GameError::ResourceLoadError(format!("invalid saved level: {}", save.level)));
+        }
+        let find = |id: &String| data_decks
+            .find(id)
+            .cloned()
+            .ok_or_else(|| GameError::ResourceLoadError(format!("card not defined: {}", id)));
+        let cards = |ids: &[String]| ids.iter().map(find).collect::<Result<Vec<_>>>();
+        let decks = Decks {
+            draw: cards(&save.run_deck)?,
+            ..data_decks.clone()
+        };
+        let mut field = Field::new_pending(save.level);
+        field.player = Some(Player {
+            creature: ActiveCreature::from(save.player.into_creature()),
+            cell: 0,
+        });
+        field.player_coins = save.coins;
+        for (cell, id) in field.cells.iter_mut().zip(&save.cells) {
+            if let Some(id) = id {
+                let card = VisibleCard::new(find(id)?, Rect {
+                    x: cell.position.0,
+                    y: cell.position.1 + 50.0 + CARD_HEIGHT / 2.0,
+                    w: CARD_WIDTH,
+                    h: CARD_HEIGHT,
+                });
+                cell.enemy = card.get_creature().map(Into::into);
+                cell.card = Some(card);
+            }
+        }
+        let mut state = GameState::with_field(&decks, save.level, field);
+        state.deck = cards(&save.deck)?;
+        state.discards = cards(&save.discards)?;
+        state.trap_deck = cards(&save.trap_deck)?;
+        state.trap_discards = cards(&save.trap_discards)?;
+        for card in cards(&save.hand)? {
+            state.hand.push(VisibleCard::new(card, Rect {
+                x: 10.0 + CARD_WIDTH / 2.0,
+                y: SCREEN_HEIGHT - 10.0 - CARD_HEIGHT / 2.0,
+                w: CARD_WIDTH,
+                h: CARD_HEIGHT,
+            }));
+        }
+        for card in cards(&save.boss_bonuses)? {
+            if let CardEffect::BossBuff(buff) = &card.effect {
+                if let Some(boss) = state.boss_mut() {
+                    boss.creature.buffs.push(buff.clone());
+                }
+            }
+            state.boss_bonuses.push(card);
+        }
+        state.mulligan_used = save.mulligan_used;
+        state.layout_cards(false);
+        Ok(state)
+    }
+
+    pub fn save(&self) -> Option<RunSave> {
+        let player = self.field.player.as_ref()?;
+        if !self.preparing {
+            return self.checkpoint.clone();
+        }
+        let ids = |cards: &[Card]| cards.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        Some(RunSave {
+            level: LEVEL_COUNT - 1 - self.pending_fields.len(),
+            player: save::Player::from_creature(&player.creature.creature),
+            coins: self.field.player_coins,
+            run_deck: ids(&self.decks.draw),
+            deck: ids(&self.deck),
+            discards: ids(&self.discards),
+            trap_deck: ids(&self.trap_deck),
+            trap_discards: ids(&self.trap_discards),
+            hand: self.hand
+                .iter()
+                .chain(&self.drag)
+                .chain(&self.selected)
+                .map(|c| c.card.id.clone())
+                .collect(),
+            cells: self.field.cells
+                .iter()
+                .map(|cell| cell.card.as_ref().map(|c| c.card.id.clone()))
+                .collect(),
+            boss_bonuses: ids(&self.boss_bonuses),
+            mulligan_used: self.mulligan_used,
+        })
+    }
+
+    /// Moves the player to the start of the next field, discarding the
+    /// cards of the current one.
+    fn next_level(&mut self) {
+        let coins = self.field.player_coins;
+        let mut player = self.field.player.take().unwrap();
+        player.cell = 0;
+        for cell in &mut self.field.cells {
+            if let Some(card) = cell.card.take() {
+                if cell.fixed {
+                    self.trap_discards.push(card.card);
+                } else {
+                    self.discards.push(card.card);
+                }
+            }
+        }
+        if let Some(card) = self.drag.take() {
+            self.hand.push(card);
+            self.drag_from = None;
+        }
+        self.discards.extend(self.hand.drain(..).map(|card| card.card));
+        self.field = self.pending_fields.remove(0);
+        self.field.player_coins = coins;
+        self.field.player = Some(player);
+        self.preparing = true;
+        self.mulligan_used = false;
+        self.draw_hand();
+        self.draw_traps();
+    }
+
+    fn boss_mut(&mut self) -> Option<&mut ActiveCreature> {
+        let field = self.pending_fields.last_mut().unwrap_or(&mut self.field);
+        field.cells.last_mut().unwrap().enemy.as_mut()
+    }
+
+    fn with_field(decks: &Decks, level: usize, field: Field) -> GameState {
         let health_label = Label::new((50.0, 10.0), |state| {
             let player = if let Some(player) = &state.field.player {
                 player
@@ -577,11 +920,12 @@ impl GameState {
                 return String::new();
             };
             if let Some(limit) = player.creature.creature.max_health {
-                format!(
-                    "{}/{}",
-                    player.creature.creature.health,
-                    limit,
-                )
+                let health = player.creature.creature.health;
+                if health * 4 <= limit {
+                    format!("[color=red][b]{}[/b][/color]/{}", health, limit)
+                } else {
+                    format!("{}/{}", health, limit)
+                }
             } else {
                 format!("{}", player.creature.creature.health)
             }
@@ -625,7 +969,14 @@ impl GameState {
             }
         });
         let level_label = Label::new((750.0, 10.0), |state| {
-            format!("Level: {}/4", 4 - state.pending_fields.len())
+            format!("Level: {}/{}", LEVEL_COUNT - state.pending_fields.len(), LEVEL_COUNT)
+        });
+        let step_label = Label::new((750.0, 50.0), |state| {
+            if state.step_mode {
+                "Step mode".to_owned()
+            } else {
+                String::new()
+            }
         });
         let deck_button = Button::new(
             Rect {
@@ -709,42 +1060,97 @@ impl GameState {
                 ViewChange::Push(Box::new(CardList::new_unsorted(cards)))
             },
         );
+        let clear_button = Button::new(
+            Rect {
+                x: 10.0,
+                y: SCREEN_HEIGHT - 350.0,
+                w: 64.0,
+                h: 64.0,
+            },
+            Icon::CROSS,
+            |state| {
+                state.clear_cells();
+                ViewChange::None
+            },
+        );
+        let mulligan_button = Button::new(
+            Rect {
+                x: 10.0,
+                y: SCREEN_HEIGHT - 424.0,
+                w: 64.0,
+                h: 64.0,
+            },
+            Icon::CARD,
+            |state| {
+                state.toggle_mulligan();
+                ViewChange::None
+            },
+        );
+        let clear_label = Label::new((84.0, SCREEN_HEIGHT - 336.0), |state| {
+            if state.preparing {
+                GameAction::ClearCells.label().to_owned()
+            } else {
+                String::new()
+            }
+        });
+        let mulligan_label = Label::new((84.0, SCREEN_HEIGHT - 410.0), |state| {
+            if !state.preparing {
+                String::new()
+            } else if state.mulligan.is_some() {
+                "Pick cards to redraw".to_owned()
+            } else {
+                match state.mulligan_rule {
+                    Mulligan::OncePerLevel if state.mulligan_used => "Redraw used".to_owned(),
+                    Mulligan::OncePerLevel => "Redraw".to_owned(),
+                    Mulligan::Coins(cost) => format!("Redraw: {} [coin]", cost),
+                }
+            }
+        });
         let mut state = GameState {
-            field: Field::new(),
-            pending_fields: vec![
-                Field::new_pending(1),
-                Field::new_pending(2),
-                Field::new_pending(3),
-            ],
-            deck: make_deck(&decks.draw),
-            trap_deck: make_deck(&decks.trap),
+            field,
+            pending_fields: (level + 1..LEVEL_COUNT).map(Field::new_pending).collect(),
+            deck: Vec::new(),
+            trap_deck: Vec::new(),
             discards: Vec::new(),
             trap_discards: Vec::new(),
             hand: Vec::new(),
             drag: None,
+            drag_from: None,
             preparing: true,
-            labels: vec![health_label, coins_label, damage_label, durability_label, level_label, draw_label, draw_trap_label, discard_label, discard_trap_label],
-            buttons: vec![deck_button, trap_deck_button, discard_button, trap_discard_button, whole_deck_button, whole_trap_deck_button, boss_preview_button],
+            labels: vec![health_label, coins_label, damage_label, durability_label, level_label, step_label, draw_label, draw_trap_label, discard_label, discard_trap_label, clear_label, mulligan_label],
+            buttons: vec![deck_button, trap_deck_button, discard_button, trap_discard_button, whole_deck_button, whole_trap_deck_button, boss_preview_button, clear_button, mulligan_button],
             boss_bonuses: Vec::new(),
             decks: decks.clone(),
+            checkpoint: None,
+            step_mode: false,
+            step_pending: false,
+            focus: None,
+            selected: None,
+            selected_from: None,
+            history: Vec::new(),
+            redo_history: Vec::new(),
+            mulligan_rule: Mulligan::default(),
+            mulligan_used: false,
+            mulligan: None,
+            inspect: None,
+            camera: Camera::new(),
         };
-        let boss_cell = state.pending_fields.last_mut().unwrap().cells.last_mut().unwrap();
-        let mut boss = VisibleCard::new(decks.boss.clone(), Rect {
-            x: boss_cell.position.0,
-            y: boss_cell.position.1 + 50.0 + CARD_HEIGHT / 2.0,
-            w: CARD_WIDTH,
-            h: CARD_HEIGHT,
-        });
-        boss_cell.card = Some(boss);
-        boss_cell.enemy = boss_cell.card.as_ref().unwrap().get_creature().map(Into::into);
-        state.layout_cards(true);
-        state.draw_hand();
-        state.draw_traps();
+        if let Some(field) = state.pending_fields.last_mut() {
+            let boss_cell = field.cells.last_mut().unwrap();
+            let boss = VisibleCard::new(decks.boss.clone(), Rect {
+                x: boss_cell.position.0,
+                y: boss_cell.position.1 + 50.0 + CARD_HEIGHT / 2.0,
+                w: CARD_WIDTH,
+                h: CARD_HEIGHT,
+            });
+            boss_cell.card = Some(boss);
+            boss_cell.enemy = boss_cell.card.as_ref().unwrap().get_creature().map(Into::into);
+        }
         state
     }
 
     fn layout_cards(&mut self, place: bool) {
-        let hand_width = self.hand.len() as f32 * CARD_WIDTH + (self.hand.len() - 1) as f32 * CARD_WIDTH * 0.1;
+        let hand_width = self.hand.len() as f32 * CARD_WIDTH + self.hand.len().saturating_sub(1) as f32 * CARD_WIDTH * 0.1;
         let start_x = SCREEN_WIDTH / 2.0 - hand_width / 2.0;
         for (i, card) in self.hand.iter_mut().enumerate() {
             let x = i as f32 * CARD_WIDTH * 1.1 + start_x;
@@ -757,15 +1163,18 @@ impl GameState {
             };
             if place {
                 card.pos = card.target_pos;
+                card.previous = (card.pos.x, card.pos.y);
             }
         }
     }
 
     fn draw_hand(&mut self) {
-        loop {
-            if self.hand.len() >= 6 {
-                break;
-            }
+        self.draw_cards(6usize.saturating_sub(self.hand.len()));
+    }
+
+    fn draw_cards(&mut self, count: usize) {
+        let mut drawn = 0;
+        while drawn < count {
             if let Some(card) = self.deck.pop() {
                 let mut card = VisibleCard::new(card, Rect {
                     x: 10.0 + CARD_WIDTH / 2.0,
@@ -775,7 +1184,8 @@ impl GameState {
                 });
                 card.target_pos = card.pos;
                 self.hand.insert(0, card);
-            } else if self.discards.len() > 0 {
+                drawn += 1;
+            } else if !self.discards.is_empty() {
                 self.deck = make_deck(&std::mem::take(&mut self.discards));
             } else {
                 break;
@@ -786,13 +1196,15 @@ impl GameState {
     fn draw_traps(&mut self) {
         for cell in self.field.cells.iter_mut().skip(1) {
             if cell.fixed {
-                if self.trap_deck.len() == 0 && self.trap_discards.len() > 0 {
+                if self.trap_deck.is_empty() && !self.trap_discards.is_empty() {
                     self.trap_deck = make_deck(&std::mem::take(&mut self.trap_discards));
                 }
                 if let Some(card) = self.trap_deck.pop() {
+                    // Dealt from the deck in the corner of the screen.
+                    let (x, y) = self.camera.to_world((10.0 + CARD_WIDTH / 2.0, SCREEN_HEIGHT - 10.0 - CARD_HEIGHT / 2.0));
                     let mut card = VisibleCard::new(card, Rect {
-                        x: 10.0 + CARD_WIDTH / 2.0,
-                        y: SCREEN_HEIGHT - 10.0 - CARD_HEIGHT / 2.0,
+                        x,
+                        y,
                         w: CARD_WIDTH,
                         h: CARD_HEIGHT,
                     });
@@ -805,14 +1217,480 @@ impl GameState {
         }
     }
 
+    fn free_cells(&self) -> Vec<usize> {
+        self.field.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.fixed && cell.card.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn movable_cells(&self) -> Vec<usize> {
+        self.field.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.fixed && cell.card.is_some())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn return_to_hand(&mut self, card: VisibleCard) {
+        self.hand.push(card);
+        self.hand.sort_by(|a, b| a.pos.x.partial_cmp(&b.pos.x).unwrap());
+    }
+
+    fn take_from_hand(&mut self, id: &str) -> Option<VisibleCard> {
+        let index = self.hand.iter().position(|c| c.card.id == id)?;
+        Some(self.hand.remove(index))
+    }
+
+    fn return_selected(&mut self) {
+        if let Some(card) = self.selected.take() {
+            let from = self.selected_from.take();
+            self.finish_move(card, from, None);
+        }
+    }
+
+    /// Puts the selected card back where it was picked up from, without
+    /// recording a move.
+    fn put_back_selected(&mut self) {
+        if let Some(card) = self.selected.take() {
+            match self.selected_from.take() {
+                Some(index) => self.place_card(index, card),
+                None => self.return_to_hand(card),
+            }
+        }
+    }
+
+    /// Moves a card from screen coordinates onto a cell.
+    fn place_card(&mut self, index: usize, mut card: VisibleCard) {
+        let (x, y) = self.camera.to_world((card.pos.x, card.pos.y));
+        card.pos.x = x;
+        card.pos.y = y;
+        card.previous = self.camera.to_world(card.previous);
+        let cell = &mut self.field.cells[index];
+        let (x, y) = cell.card_position();
+        card.target_pos.x = x;
+        card.target_pos.y = y;
+        cell.enemy = card.get_creature().map(Into::into);
+        cell.card = Some(card);
+    }
+
+    /// Takes a card from a cell, moving it to screen coordinates.
+    fn take_card(&mut self, index: usize) -> Option<VisibleCard> {
+        let cell = &mut self.field.cells[index];
+        cell.enemy = None;
+        let mut card = cell.card.take()?;
+        let (x, y) = self.camera.to_screen((card.pos.x, card.pos.y));
+        card.pos.x = x;
+        card.pos.y = y;
+        card.previous = self.camera.to_screen(card.previous);
+        Some(card)
+    }
+
+    fn screen_rect(&self, rect: Rect) -> Rect {
+        let (x, y) = self.camera.to_screen((rect.x, rect.y));
+        let zoom = self.camera.zoom();
+        Rect { x, y, w: rect.w * zoom, h: rect.h * zoom }
+    }
+
+    /// Where the camera should look: following the player along the field,
+    /// and closer when fighting the boss.
+    fn camera_target(&self) -> ((f32, f32), f32) {
+        let cells = &self.field.cells;
+        let player = match &self.field.player {
+            Some(player) => player,
+            None => return (self.camera.center(), self.camera.zoom()),
+        };
+        let position = cells[player.cell].position;
+        let boss_fight = self.pending_fields.is_empty()
+            && player.cell == cells.len() - 1
+            && cells[player.cell].enemy.is_some();
+        let (center, zoom) = if boss_fight {
+            ((position.0, position.1 + 60.0), BOSS_ZOOM)
+        } else {
+            ((position.0, SCREEN_HEIGHT / 2.0), 1.0)
+        };
+        // Keep the view inside the field, which has the same margin on both
+        // sides as in front of the first cell.
+        let right = SCREEN_WIDTH.max(cells[cells.len() - 1].position.0 + cells[0].position.0);
+        let half_width = SCREEN_WIDTH / 2.0 / zoom;
+        let half_height = SCREEN_HEIGHT / 2.0 / zoom;
+        let x = center.0.clamp(half_width, right - half_width);
+        let y = center.1.clamp(half_height, SCREEN_HEIGHT - half_height);
+        ((x, y), zoom)
+    }
+
+    /// Puts down a card that was picked up from cell `from` (or from the
+    /// hand) onto cell `to` (or into the hand), and records the move.
+    fn finish_move(&mut self, card: VisibleCard, from: Option<usize>, to: Option<usize>) {
+        let id = card.card.id.clone();
+        if to.is_some() {
+            self.field.sounds.push(sounds::CARD_DROP);
+        }
+        match to {
+            Some(index) => self.place_card(index, card),
+            None => self.return_to_hand(card),
+        }
+        if from != to {
+            self.record(Placement {
+                removed: from.map(|cell| (cell, id.clone())).into_iter().collect(),
+                placed: to.map(|cell| (cell, id)).into_iter().collect(),
+            });
+        }
+    }
+
+    fn record(&mut self, placement: Placement) {
+        self.history.push(placement);
+        self.redo_history.clear();
+    }
+
+    fn apply_placement(&mut self, placement: &Placement) {
+        for &(cell, _) in &placement.removed {
+            if let Some(card) = self.take_card(cell) {
+                self.return_to_hand(card);
+            }
+        }
+        for (cell, id) in &placement.placed {
+            if let Some(card) = self.take_from_hand(id) {
+                self.place_card(*cell, card);
+            }
+        }
+    }
+
+    fn revert_placement(&mut self, placement: &Placement) {
+        for &(cell, _) in placement.placed.iter().rev() {
+            if let Some(card) = self.take_card(cell) {
+                self.return_to_hand(card);
+            }
+        }
+        for (cell, id) in placement.removed.iter().rev() {
+            if let Some(card) = self.take_from_hand(id) {
+                self.place_card(*cell, card);
+            }
+        }
+    }
+
+    fn can_edit_placement(&self) -> bool {
+        self.preparing && self.drag.is_none() && self.mulligan.is_none()
+    }
+
+    fn can_mulligan(&self) -> bool {
+        if !self.can_edit_placement() || self.field.player.is_none() {
+            return false;
+        }
+        match self.mulligan_rule {
+            Mulligan::OncePerLevel => !self.mulligan_used,
+            Mulligan::Coins(cost) => self.field.player_coins >= cost,
+        }
+    }
+
+    /// Starts picking cards to redraw, or redraws the picked ones if
+    /// already picking.
+    fn toggle_mulligan(&mut self) {
+        match self.mulligan.take() {
+            None if self.can_mulligan() => {
+                self.return_selected();
+                self.focus = None;
+                self.mulligan = Some(Vec::new());
+            }
+            None => {}
+            Some(picked) => self.mulligan_cards(&picked),
+        }
+    }
+
+    fn mulligan_cards(&mut self, picked: &[usize]) {
+        if picked.is_empty() || !self.can_mulligan() {
+            return;
+        }
+        match self.mulligan_rule {
+            Mulligan::OncePerLevel => self.mulligan_used = true,
+            Mulligan::Coins(cost) => self.field.player_coins -= cost,
+        }
+        for (index, card) in std::mem::take(&mut self.hand).into_iter().enumerate() {
+            if picked.contains(&index) {
+                self.deck.push(card.card);
+            } else {
+                self.hand.push(card);
+            }
+        }
+        self.deck = make_deck(&self.deck);
+        // Only replace the redrawn cards, the hand is short by the ones
+        // already placed on the field.
+        self.draw_cards(picked.len());
+        self.focus = None;
+        // Placements refer to cards that may have left the hand.
+        self.history.clear();
+        self.redo_history.clear();
+    }
+
+    fn update_mulligan(&mut self, ctx: &Ctx<'_>) {
+        if self.mulligan.is_none() {
+            return;
+        }
+        if ctx.is_action_click(Action::Back) {
+            self.mulligan = None;
+            self.focus = None;
+            return;
+        }
+        if ctx.is_mouse_moved() {
+            self.focus = None;
+        }
+        let forward = ctx.is_action_click(Action::Right) || ctx.is_action_click(Action::NextCard);
+        let backward = ctx.is_action_click(Action::Left) || ctx.is_action_click(Action::PreviousCard);
+        let current = match self.focus {
+            Some(Focus::Hand(index)) if index < self.hand.len() => Some(index),
+            _ => None,
+        };
+        if forward || backward {
+            let hand = (0..self.hand.len()).collect::<Vec<_>>();
+            self.focus = cycle(&hand, current, forward).map(Focus::Hand);
+        }
+        let (x, y) = ctx.mouse_position();
+        let toggle = if ctx.is_mouse_click() {
+            self.hand.iter().rposition(|card| card.visual_rect().contains(x, y))
+        } else if ctx.is_action_click(Action::Confirm) {
+            current
+        } else {
+            None
+        };
+        if let (Some(index), Some(picked)) = (toggle, &mut self.mulligan) {
+            match picked.iter().position(|&i| i == index) {
+                Some(position) => { picked.remove(position); }
+                None => picked.push(index),
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if !self.can_edit_placement() {
+            return;
+        }
+        self.put_back_selected();
+        if let Some(placement) = self.history.pop() {
+            self.revert_placement(&placement);
+            self.redo_history.push(placement);
+        }
+    }
+
+    fn redo(&mut self) {
+        if !self.can_edit_placement() {
+            return;
+        }
+        self.put_back_selected();
+        if let Some(placement) = self.redo_history.pop() {
+            self.apply_placement(&placement);
+            self.history.push(placement);
+        }
+    }
+
+    fn clear_cells(&mut self) {
+        if !self.can_edit_placement() {
+            return;
+        }
+        self.put_back_selected();
+        let removed = self.movable_cells()
+            .into_iter()
+            .filter_map(|index| {
+                let card = self.take_card(index)?;
+                let id = card.card.id.clone();
+                self.return_to_hand(card);
+                Some((index, id))
+            })
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
+            self.record(Placement { removed, placed: Vec::new() });
+        }
+    }
+
+    fn update_focus(&mut self, ctx: &Ctx<'_>) {
+        if !self.preparing {
+            self.return_selected();
+            self.focus = None;
+            return;
+        }
+        if self.mulligan.is_some() {
+            return;
+        }
+        if ctx.is_mouse_moved() && self.selected.is_none() {
+            self.focus = None;
+        }
+        let forward = ctx.is_action_click(Action::Right) || ctx.is_action_click(Action::NextCard);
+        let backward = ctx.is_action_click(Action::Left) || ctx.is_action_click(Action::PreviousCard);
+        let up = ctx.is_action_click(Action::Up);
+        let down = ctx.is_action_click(Action::Down);
+        let confirm = ctx.is_action_click(Action::Confirm);
+        let hand = (0..self.hand.len()).collect::<Vec<_>>();
+
+        if self.selected.is_some() {
+            let free = self.free_cells();
+            let current = match self.focus {
+                Some(Focus::Cell(cell)) if free.contains(&cell) => Some(cell),
+                _ => None,
+            };
+            let target = if forward || backward || current.is_none() {
+                cycle(&free, current, !backward)
+            } else {
+                current
+            };
+            let target = match target {
+                Some(target) if !ctx.is_action_click(Action::Back) => target,
+                _ => {
+                    self.return_selected();
+                    self.focus = Some(Focus::Hand(0));
+                    return;
+                }
+            };
+            self.focus = Some(Focus::Cell(target));
+            if confirm {
+                let card = self.selected.take().unwrap();
+                let from = self.selected_from.take();
+                self.finish_move(card, from, Some(target));
+                self.focus = Some(Focus::Hand(0));
+            }
+            return;
+        }
+
+        let movable = self.movable_cells();
+        self.focus = match self.focus {
+            Some(Focus::Hand(index)) if index < hand.len() => {
+                if up && !movable.is_empty() {
+                    Some(Focus::Cell(movable[0]))
+                } else if forward || backward {
+                    cycle(&hand, Some(index), forward).map(Focus::Hand)
+                } else if confirm {
+                    self.selected = Some(self.hand.remove(index));
+                    self.field.sounds.push(sounds::CARD_PICKUP);
+                    None
+                } else {
+                    Some(Focus::Hand(index))
+                }
+            }
+            Some(Focus::Cell(index)) if movable.contains(&index) => {
+                if down && !hand.is_empty() {
+                    Some(Focus::Hand(0))
+                } else if forward || backward {
+                    cycle(&movable, Some(index), forward).map(Focus::Cell)
+                } else if confirm {
+                    self.selected = self.take_card(index);
+                    self.selected_from = Some(index);
+                    self.field.sounds.push(sounds::CARD_PICKUP);
+                    Some(Focus::Cell(index))
+                } else {
+                    Some(Focus::Cell(index))
+                }
+            }
+            _ if up && !movable.is_empty() => Some(Focus::Cell(movable[0])),
+            _ if forward || backward || up || down || confirm => {
+                cycle(&hand, None, !backward).map(Focus::Hand)
+            }
+            _ => None,
+        };
+        if let Some(Focus::Hand(index)) = self.focus {
+            if index >= self.hand.len() {
+                self.focus = cycle(&hand, None, true).map(Focus::Hand);
+            }
+        }
+    }
+
+    fn lift_focused(&mut self) {
+        if !self.preparing {
+            return;
+        }
+        if let Some(Focus::Hand(index)) = self.focus {
+            if let Some(card) = self.hand.get_mut(index) {
+                card.target_pos.y -= FOCUS_LIFT;
+            }
+        }
+        for (index, cell) in self.field.cells.iter_mut().enumerate() {
+            let (x, y) = cell.card_position();
+            let lift = if self.focus == Some(Focus::Cell(index)) { FOCUS_LIFT } else { 0.0 };
+            if let Some(card) = &mut cell.card {
+                card.target_pos.x = x;
+                card.target_pos.y = y - lift;
+            } else if let (Some(card), true) = (&mut self.selected, lift > 0.0) {
+                // The selected card is still in screen coordinates.
+                let (x, y) = self.camera.to_screen((x, y - lift));
+                card.target_pos.x = x;
+                card.target_pos.y = y;
+            }
+        }
+    }
+
+    fn update_inspect(&mut self, ctx: &Ctx<'_>) {
+        if self.drag.is_some() || self.selected.is_some() {
+            self.inspect = None;
+            return;
+        }
+        if let Some(focus) = self.focus {
+            self.inspect = Some(match focus {
+                Focus::Hand(index) => Inspect::Hand(index),
+                Focus::Cell(index) => Inspect::Cell(index),
+            });
+            return;
+        }
+        let (x, y) = ctx.mouse_position();
+        let hand = self.hand
+            .iter()
+            .rposition(|card| card.visual_rect().contains(x, y))
+            .map(Inspect::Hand);
+        let (x, y) = self.camera.to_world((x, y));
+        let cell = || self.field.cells
+            .iter()
+            .position(|cell| cell.card.as_ref().is_some_and(|card| card.visual_rect().contains(x, y)))
+            .map(Inspect::Cell);
+        let enemy = || self.field.cells
+            .iter()
+            .position(|cell| cell.enemy.is_some() && cell.enemy_rect().contains(x, y))
+            .map(Inspect::Enemy);
+        self.inspect = hand.or_else(cell).or_else(enemy);
+    }
+
+    fn draw_inspect(&self, renderer: &mut FrameRenderer<'_>) -> Result {
+        match self.inspect {
+            Some(Inspect::Hand(index)) => {
+                if let Some(card) = self.hand.get(index) {
+                    let lines = tooltip::describe_card(&card.card);
+                    tooltip::draw(renderer, card.visual_rect().into(), Some(&card.card), &lines)?;
+                }
+            }
+            Some(Inspect::Cell(index)) => {
+                if let Some(card) = &self.field.cells[index].card {
+                    let lines = tooltip::describe_card(&card.card);
+                    let rect = self.screen_rect(card.visual_rect());
+                    tooltip::draw(renderer, rect.into(), Some(&card.card), &lines)?;
+                }
+            }
+            Some(Inspect::Enemy(index)) => {
+                let cell = &self.field.cells[index];
+                if let Some(enemy) = &cell.enemy {
+                    let lines = tooltip::describe_creature(&enemy.creature, enemy.attack_power());
+                    tooltip::draw(renderer, self.screen_rect(cell.enemy_rect()).into(), None, &lines)?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
     fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
+        renderer.push_camera(&self.camera)?;
         self.field.render(renderer)?;
-        for card in &self.hand {
+        renderer.pop_transform()?;
+        for (index, card) in self.hand.iter().enumerate() {
             card.draw(renderer)?;
+            if self.mulligan.as_ref().is_some_and(|picked| picked.contains(&index)) {
+                let rect = card.visual_rect();
+                renderer.draw_shade(rect.x, rect.y, rect.w, rect.h, 0.5)?;
+            }
         }
         if let Some(card) = &self.drag {
             card.draw(renderer)?;
         }
+        if let Some(card) = &self.selected {
+            card.draw(renderer)?;
+        }
 
         if let Some(player) = &self.field.player {
             renderer.draw_icon(Icon::HEART, 10.0, 10.0, 32.0, 32.0)?;
@@ -822,8 +1700,8 @@ impl GameState {
                 renderer.draw_icon(Icon::SWORD, 10.0, 130.0, 32.0, 32.0)?;
             }
         }
-        for label in &self.labels {
-            engine::ggez::graphics::queue_text(renderer.ggez(), &label.text, [label.position.0, label.position.1], Some(engine::ggez::graphics::BLACK));
+        for label in &mut self.labels {
+            renderer.queue_text(&mut label.text, label.position.0, label.position.1, engine::ggez::graphics::BLACK)?;
         }
         for button in &self.buttons {
             renderer.draw_icon(button.icon, button.bounds.x, button.bounds.y, button.bounds.w, button.bounds.h)?;
@@ -832,54 +1710,148 @@ impl GameState {
     }
 }
 
+/// Cheats for the developer console.
+#[cfg(feature = "dev")]
+impl GameState {
+    pub fn action_state(&self) -> String {
+        format!("{:?}", self.field.action)
+    }
+
+    pub fn spawn_card(&mut self, card: Card) {
+        self.hand.push(VisibleCard::new(card, Rect {
+            x: 10.0 + CARD_WIDTH / 2.0,
+            y: SCREEN_HEIGHT - 10.0 - CARD_HEIGHT / 2.0,
+            w: CARD_WIDTH,
+            h: CARD_HEIGHT,
+        }));
+    }
+
+    fn player_mut(&mut self) -> std::result::Result<&mut Creature, String> {
+        match &mut self.field.player {
+            Some(player) => Ok(&mut player.creature.creature),
+            None => Err("the player is dead".to_owned()),
+        }
+    }
+
+    pub fn set_health(&mut self, health: u32) -> std::result::Result<(), String> {
+        let player = self.player_mut()?;
+        player.health = health;
+        if let Some(limit) = &mut player.max_health {
+            *limit = (*limit).max(health);
+        }
+        Ok(())
+    }
+
+    pub fn set_coins(&mut self, coins: u32) -> std::result::Result<(), String> {
+        self.player_mut()?;
+        self.field.player_coins = coins;
+        Ok(())
+    }
+
+    /// Skips forward to the preparation of `level`, counting from 1.
+    pub fn skip_to_level(&mut self, level: usize) -> std::result::Result<(), String> {
+        let current = LEVEL_COUNT - self.pending_fields.len();
+        if level <= current || level > LEVEL_COUNT {
+            return Err(format!("can only skip to levels {} to {}", current + 1, LEVEL_COUNT));
+        }
+        self.player_mut()?;
+        self.return_selected();
+        self.focus = None;
+        self.inspect = None;
+        self.mulligan = None;
+        self.history.clear();
+        self.redo_history.clear();
+        for _ in current..level {
+            self.next_level();
+        }
+        Ok(())
+    }
+
+    pub fn buff_boss(&mut self, card: Card) -> std::result::Result<(), String> {
+        let buff = match &card.effect {
+            CardEffect::BossBuff(buff) => buff.clone(),
+            _ => return Err(format!("{} is not a boss buff", card.id)),
+        };
+        let boss = self.boss_mut().ok_or_else(|| "the boss is dead".to_owned())?;
+        boss.creature.buffs.push(buff);
+        self.boss_bonuses.push(card);
+        Ok(())
+    }
+
+    /// Ids of the trap cards, in the order they will be dealt.
+    pub fn trap_order(&self) -> Vec<&str> {
+        self.trap_deck.iter().rev().map(|card| card.id.as_str()).collect()
+    }
+}
+
 impl View for GameState {
     fn draw_kind(&self) -> DrawKind {
         DrawKind::Opaque
     }
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+    #[cfg(feature = "dev")]
+    fn as_game_state(&mut self) -> Option<&mut GameState> {
+        Some(self)
+    }
+
+    fn music(&self) -> Option<&'static str> {
+        Some(sounds::GAME_MUSIC)
+    }
+
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, dt: f32) -> Result<ViewChange> {
+        let bindings = &data.options.key_bindings;
+        self.mulligan_rule = data.options.mulligan;
+        let pause = bindings.is_click(ctx, GameAction::Pause) &&
+            self.selected.is_none() &&
+            self.mulligan.is_none();
+        if pause || ctx.is_action_click(Action::Pause) {
+            return Ok(ViewChange::Push(Box::new(MenuView::new(Pause::new(self.save())))));
+        }
+
+        if bindings.is_click(ctx, GameAction::OpenDeck) {
+            return Ok(ViewChange::Push(Box::new(CardList::new(self.deck.clone()))));
+        }
+        if bindings.is_click(ctx, GameAction::OpenDiscards) {
+            return Ok(ViewChange::Push(Box::new(CardList::new(self.discards.clone()))));
+        }
+
+        if bindings.is_click(ctx, GameAction::StepMode) {
+            self.step_mode = !self.step_mode;
+        }
+
+        self.field.effects.update(dt);
         if !self.preparing {
-            self.field.update_action(dt * 3.0);
+            let mut speed = WALK_SPEED * data.options.game_speed;
+            if bindings.is_pressed(ctx, GameAction::FastForward) {
+                speed *= FAST_FORWARD_SPEED;
+            }
+            if self.step_mode && bindings.is_click(ctx, GameAction::StartWalk) {
+                self.step_pending = true;
+            }
+            if !self.step_mode || self.step_pending {
+                let changed = self.field.update_action(dt * speed);
+                if changed && self.step_mode {
+                    self.step_pending = false;
+                }
+            }
             for card in self.field.discards.drain(..) {
                 self.trap_discards.push(card);
             }
-            for card in self.field.boss_bonuses.drain(..) {
+            for card in std::mem::take(&mut self.field.boss_bonuses) {
                 let buff = match &card.effect {
                     CardEffect::BossBuff(buff) => buff.clone(),
                     _ => panic!("bad boss bonus"),
                 };
                 self.boss_bonuses.push(card);
-                if self.pending_fields.len() == 0 {
-                    let boss = self.field.cells.last_mut().unwrap().enemy.as_mut().unwrap();
-                    boss.creature.buffs.push(buff);
-                } else {
-                    let boss = self.pending_fields.last_mut().unwrap().cells.last_mut().unwrap().enemy.as_mut().unwrap();
-                    boss.creature.buffs.push(buff);
-                }
+                self.boss_mut().unwrap().creature.buffs.push(buff);
             }
             match self.field.action {
-                ActionState::Finished(t) if t >= 0.5 && self.field.player.is_some() && self.pending_fields.len() > 0 => {
-                    let coins = self.field.player_coins;
-                    let mut player = self.field.player.take().unwrap();
-                    player.cell = 0;
-                    for cell in &mut self.field.cells {
-                        if let Some(card) = cell.card.take() {
-                            if cell.fixed {
-                                self.trap_discards.push(card.card);
-                            } else {
-                                self.discards.push(card.card);
-                            }
-                        }
-                    }
-                    self.field = self.pending_fields.remove(0);
-                    self.field.player_coins = coins;
-                    self.field.player = Some(player);
-                    self.preparing = true;
-                    self.draw_hand();
-                    self.draw_traps();
+                ActionState::Finished(t) if t >= 0.5 && self.field.player.is_some() && !self.pending_fields.is_empty() => {
+                    self.next_level();
                 }
-                ActionState::Finished(t) if t >= 1.5 && self.field.player.is_some() && self.pending_fields.len() == 0 => {
-                    return Ok(ViewChange::Replace(Box::new(super::CardSelect::new(self.decks.clone()))));
+                ActionState::Finished(t) if t >= 1.5 && self.field.player.is_some() && self.pending_fields.is_empty() => {
+                    let select = super::CardSelect::new(self.decks.clone());
+                    return Ok(ViewChange::Replace(Box::new(select)).with_transition(Transition::Crossfade));
                 }
                 _ => {}
             }
@@ -906,57 +1878,65 @@ impl View for GameState {
                 card.pos.x = mouse_x;
                 card.pos.y = mouse_y;
                 card.target_pos = card.pos;
+                // Stays right under the mouse instead of trailing it.
+                card.previous = (mouse_x, mouse_y);
             }
             Some(card) => {
-                let x = card.pos.x;
-                let y = card.pos.y;
-                if self.preparing {
-                    for cell in &mut self.field.cells {
-                        if cell.card.is_some() || cell.fixed {
-                            continue;
-                        }
-                        let rect = cell.drop_rect();
-                        if rect.contains(x, y) {
-                            card.target_pos.x = cell.position.0;
-                            card.target_pos.y = cell.position.1 + 50.0 + CARD_HEIGHT / 2.0;
-                            cell.card = self.drag.take();
-                            cell.enemy = cell.card.as_ref().unwrap().get_creature().map(Into::into);
-                            break;
-                        }
-                    }
-                }
-                if let Some(card) = self.drag.take() {
-                    self.hand.push(card);
-                    self.hand.sort_by(|a, b| a.pos.x.partial_cmp(&b.pos.x).unwrap());
-                }
+                let (x, y) = self.camera.to_world((card.pos.x, card.pos.y));
+                let target = if self.preparing {
+                    self.field.cells
+                        .iter()
+                        .position(|cell| cell.card.is_none() && !cell.fixed && cell.drop_rect().contains(x, y))
+                } else {
+                    None
+                };
+                let card = self.drag.take().unwrap();
+                let from = self.drag_from.take();
+                self.finish_move(card, from, target);
             }
-            None if mouse_pressed => {
+            None if mouse_pressed && self.mulligan.is_none() => {
                 for (index, card) in self.hand.iter().enumerate() {
                     let rect = card.visual_rect();
                     if rect.contains(mouse_x, mouse_y) {
                         self.drag = Some(self.hand.remove(index));
+                        self.field.sounds.push(sounds::CARD_PICKUP);
                         break;
                     }
                 }
                 if self.drag.is_none() && self.preparing {
-                    for cell in &mut self.field.cells {
-                        if cell.fixed {
-                            continue;
-                        }
-                        if let Some(card) = &cell.card {
-                            let rect = card.visual_rect();
-                            if rect.contains(mouse_x, mouse_y) {
-                                self.drag = cell.card.take();
-                                cell.enemy = None;
-                                break;
-                            }
-                        }
+                    let (mouse_x, mouse_y) = self.camera.to_world((mouse_x, mouse_y));
+                    let target = self.field.cells
+                        .iter()
+                        .position(|cell| !cell.fixed && cell.card.as_ref().is_some_and(|card| {
+                            card.visual_rect().contains(mouse_x, mouse_y)
+                        }));
+                    if let Some(index) = target {
+                        self.drag = self.take_card(index);
+                        self.drag_from = Some(index);
+                        self.field.sounds.push(sounds::CARD_PICKUP);
                     }
                 }
             }
             None => {}
         }
+        let bindings = &data.options.key_bindings;
+        if bindings.is_click(ctx, GameAction::Undo) {
+            self.undo();
+        }
+        if bindings.is_click(ctx, GameAction::Redo) {
+            self.redo();
+        }
+        if bindings.is_click(ctx, GameAction::ClearCells) {
+            self.clear_cells();
+        }
+        if bindings.is_click(ctx, GameAction::Mulligan) {
+            self.toggle_mulligan();
+        }
+        self.update_mulligan(ctx);
+        self.update_focus(ctx);
+        self.update_inspect(ctx);
         self.layout_cards(false);
+        self.lift_focused();
         for cell in &mut self.field.cells {
             if let Some(card) = &mut cell.card {
                 card.update(dt);
@@ -965,20 +1945,33 @@ impl View for GameState {
         for card in &mut self.hand {
             card.update(dt);
         }
+        if let Some(card) = &mut self.selected {
+            card.update(dt);
+        }
 
-        if engine::ggez::input::keyboard::is_key_pressed(
-            ctx.ggez(),
-            engine::ggez::input::keyboard::KeyCode::Space,
-        ) {
-            if self.preparing {
-                println!("finished preparation");
-            }
+        let start_walk = data.options.key_bindings.is_pressed(ctx, GameAction::StartWalk);
+        if start_walk && self.preparing && self.mulligan.is_none() {
+            println!("finished preparation");
+            self.return_selected();
+            self.focus = None;
+            self.history.clear();
+            self.redo_history.clear();
+            self.checkpoint = self.save();
             self.preparing = false;
+            self.step_pending = true;
+        }
+
+        for sound in self.field.sounds.drain(..) {
+            ctx.audio().play_sound(sound);
         }
+        self.camera.shake(std::mem::take(&mut self.field.shake));
+        let (center, zoom) = self.camera_target();
+        self.camera.look_at(center, zoom);
+        self.camera.update(dt);
 
         let mut labels = std::mem::take(&mut self.labels);
         for label in &mut labels {
-            label.text.fragments_mut()[0].text = (label.get_text)(self);
+            label.text.set_contents(&(label.get_text)(self));
         }
         self.labels = labels;
         Ok(ViewChange::None)
@@ -986,12 +1979,54 @@ impl View for GameState {
 
     fn draw(&mut self, renderer: &mut FrameRenderer<'_>) -> Result {
         self.draw(renderer)?;
-        engine::ggez::graphics::draw_queued_text(
-            renderer.ggez(),
-            engine::ggez::graphics::DrawParam::default(),
-            None,
-            engine::ggez::graphics::FilterMode::Linear,
-        )?;
+        renderer.draw_queued_text()?;
+        self.draw_inspect(renderer)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use engine::Texture;
+    use super::*;
+
+    fn card(id: String) -> Card {
+        Card {
+            id,
+            texture: Texture::default(),
+            effect: CardEffect::None,
+        }
+    }
+
+    fn decks() -> Decks {
+        Decks {
+            draw: (0..12).map(|i| card(format!("draw-{}", i))).collect(),
+            trap: (0..8).map(|i| card(format!("trap-{}", i))).collect(),
+            treasure: Vec::new(),
+            boss: card("boss".to_owned()),
+        }
+    }
+
+    fn placed_count(state: &GameState) -> usize {
+        state.field.cells.iter().filter(|cell| !cell.fixed && cell.card.is_some()).count()
+    }
+
+    #[test]
+    fn mulligan_keeps_card_count_with_placed_cards() {
+        let mut state = GameState::new(&decks());
+        for index in state.free_cells().into_iter().take(2) {
+            let card = state.hand.pop().unwrap();
+            state.place_card(index, card);
+        }
+        let hand = state.hand.len();
+        let total = hand + placed_count(&state) + state.deck.len() + state.discards.len();
+        assert_eq!(hand, 4);
+
+        state.mulligan_cards(&[0, 1]);
+
+        assert!(state.mulligan_used);
+        assert_eq!(state.hand.len(), hand);
+        assert_eq!(placed_count(&state), 2);
+        assert_eq!(state.hand.len() + placed_count(&state) + state.deck.len() + state.discards.len(), total);
+    }
+}
@@ -1,15 +1,29 @@
 use engine::{Ctx, Result};
+use engine::ggez::Context;
 use crate::GameData;
-use super::{ButtonSpec, ButtonState, MenuSpec, MenuView, ViewChange};
+use crate::save;
+use crate::sounds;
+use super::{ButtonSpec, ButtonState, MenuSpec, MenuView, Transition, ViewChange};
 use super::settings::Settings;
 
 #[derive(Clone)]
 pub enum Input {
+    Continue,
     Play,
     Settings,
 }
 
-pub struct MainMenu;
+pub struct MainMenu {
+    has_save: bool,
+}
+
+impl MainMenu {
+    pub fn new(ctx: &Context) -> MainMenu {
+        MainMenu {
+            has_save: save::exists(ctx),
+        }
+    }
+}
 
 impl MenuSpec for MainMenu {
     type Input = Input;
@@ -18,33 +32,73 @@ impl MenuSpec for MainMenu {
         360.0
     }
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, input: Self::Input) -> Result<ViewChange> {
+    fn music() -> Option<&'static str> {
+        Some(sounds::MENU_MUSIC)
+    }
+
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, input: Self::Input) -> Result<ViewChange> {
         Ok(match input {
-            Input::Play => ViewChange::Replace(Box::new(super::game::GameState::new(&data.decks))),
-            Input::Settings => ViewChange::Push(Box::new(MenuView::new(Settings::new()))),
+            Input::Continue => {
+                let state = save::load(ctx.ggez()).and_then(|run| {
+                    run.map(|run| super::game::GameState::from_save(&data.decks, run)).transpose()
+                });
+                match state {
+                    Ok(Some(state)) => {
+                        save::clear(ctx.ggez())?;
+                        ViewChange::Replace(Box::new(state)).with_transition(Transition::Fade)
+                    }
+                    Ok(None) => {
+                        self.has_save = false;
+                        ViewChange::None
+                    }
+                    Err(e) => {
+                        // A broken save would fail the same way every time,
+                        // so it is thrown away instead of kept around.
+                        eprintln!("could not continue the saved run: {}", e);
+                        save::clear(ctx.ggez())?;
+                        self.has_save = false;
+                        ViewChange::None
+                    }
+                }
+            }
+            Input::Play => {
+                save::clear(ctx.ggez())?;
+                ViewChange::Replace(Box::new(super::game::GameState::new(&data.decks)))
+                    .with_transition(Transition::Fade)
+            }
+            Input::Settings => ViewChange::Push(Box::new(MenuView::new(Settings::new(&data.options))))
+                .with_transition(Transition::Slide),
         })
     }
 
     fn create_elements(&self) -> Vec<ButtonSpec<Self::Input>> {
-        vec![
-            ButtonSpec {
-                text: "Play",
-                state: ButtonState::Normal,
-                on_click: Input::Play,
-                indent_level: 0,
-            },
-            ButtonSpec {
-                text: "Settings",
+        let mut buttons = Vec::new();
+        if self.has_save {
+            buttons.push(ButtonSpec {
+                text: "Continue".into(),
                 state: ButtonState::Normal,
-                on_click: Input::Settings,
+                on_click: Input::Continue,
                 indent_level: 0,
-            },
-            // ButtonSpec {
-            //     text: "Quit",
-            //     state: ButtonState::Normal,
-            //     on_click: Action::ChangeView(|| ViewChange::None),
-            //     indent_level: 0,
-            // },
-        ]
+            });
+        }
+        buttons.push(ButtonSpec {
+            text: "Play".into(),
+            state: ButtonState::Normal,
+            on_click: Input::Play,
+            indent_level: 0,
+        });
+        buttons.push(ButtonSpec {
+            text: "Settings".into(),
+            state: ButtonState::Normal,
+            on_click: Input::Settings,
+            indent_level: 0,
+        });
+        // ButtonSpec {
+        //     text: "Quit".into(),
+        //     state: ButtonState::Normal,
+        //     on_click: Action::ChangeView(|| ViewChange::None),
+        //     indent_level: 0,
+        // },
+        buttons
     }
 }
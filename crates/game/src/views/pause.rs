@@ -0,0 +1,92 @@
+use engine::{Ctx, Result};
+use crate::GameData;
+use crate::save::{self, RunSave};
+use super::{ButtonSpec, ButtonState, DrawKind, MenuSpec, MenuView, Transition, ViewChange};
+use super::main::MainMenu;
+use super::settings::Settings;
+
+#[derive(Clone)]
+pub enum Input {
+    Resume,
+    Settings,
+    SaveAndQuit,
+    Abandon,
+}
+
+pub struct Pause {
+    save: Option<RunSave>,
+}
+
+impl Pause {
+    pub fn new(save: Option<RunSave>) -> Pause {
+        Pause { save }
+    }
+}
+
+impl MenuSpec for Pause {
+    type Input = Input;
+
+    fn top_padding() -> f32 {
+        200.0
+    }
+
+    fn draw_kind() -> DrawKind {
+        DrawKind::OnTop
+    }
+
+    fn back(&self) -> Option<Self::Input> {
+        Some(Input::Resume)
+    }
+
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, input: Self::Input) -> Result<ViewChange> {
+        Ok(match input {
+            Input::Resume => ViewChange::Pop,
+            Input::Settings => ViewChange::Push(Box::new(MenuView::new(Settings::new(&data.options))))
+                .with_transition(Transition::Slide),
+            Input::SaveAndQuit => {
+                if let Some(run) = &self.save {
+                    save::store(ctx.ggez(), run)?;
+                }
+                ViewChange::Reset(Box::new(MenuView::new(MainMenu::new(ctx.ggez()))))
+                    .with_transition(Transition::Fade)
+            }
+            Input::Abandon => {
+                save::clear(ctx.ggez())?;
+                ViewChange::Reset(Box::new(MenuView::new(MainMenu::new(ctx.ggez()))))
+                    .with_transition(Transition::Fade)
+            }
+        })
+    }
+
+    fn create_elements(&self) -> Vec<ButtonSpec<Self::Input>> {
+        let mut buttons = vec![
+            ButtonSpec {
+                text: "Resume".into(),
+                state: ButtonState::Normal,
+                on_click: Input::Resume,
+                indent_level: 0,
+            },
+            ButtonSpec {
+                text: "Settings".into(),
+                state: ButtonState::Normal,
+                on_click: Input::Settings,
+                indent_level: 0,
+            },
+        ];
+        if self.save.is_some() {
+            buttons.push(ButtonSpec {
+                text: "Save & Quit".into(),
+                state: ButtonState::Normal,
+                on_click: Input::SaveAndQuit,
+                indent_level: 0,
+            });
+        }
+        buttons.push(ButtonSpec {
+            text: "Abandon Run".into(),
+            state: ButtonState::Normal,
+            on_click: Input::Abandon,
+            indent_level: 0,
+        });
+        buttons
+    }
+}
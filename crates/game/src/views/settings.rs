@@ -1,17 +1,36 @@
-use engine::{Ctx, Result, WindowMode};
+use engine::{Action, Ctx, KeyCode, Monitor, Result, WindowMode};
 use crate::GameData;
-use super::{ButtonSpec, ButtonState, MenuSpec, MenuView, ViewChange};
+use crate::sounds;
+use crate::options::{Mulligan, Options};
+use crate::bindings::{self, GameAction, KeyBindings};
+use super::{ButtonSpec, ButtonState, MenuSpec, MenuView, Transition, ViewChange};
 
 pub struct Settings {
-    resolutions: Vec<(f32, f32, &'static str, bool)>,
+    resolutions: Vec<(f32, f32, bool)>,
     modes: Vec<(WindowMode, &'static str, bool)>,
+    monitors: Vec<(Monitor, bool)>,
+    speeds: Vec<(f32, &'static str, bool)>,
+    mulligans: Vec<(Mulligan, &'static str, bool)>,
+    bindings: Vec<(GameAction, KeyCode)>,
+    rebinding: Option<GameAction>,
+    show_fps: bool,
+    music_volume: f32,
+    effects_volume: f32,
 }
 
 impl Settings {
-    pub fn new() -> Settings {
+    pub fn new(options: &Options) -> Settings {
         Settings {
             resolutions: Vec::new(),
             modes: Vec::new(),
+            monitors: Vec::new(),
+            speeds: Vec::new(),
+            mulligans: Vec::new(),
+            bindings: Vec::new(),
+            rebinding: None,
+            show_fps: options.show_fps,
+            music_volume: options.music_volume,
+            effects_volume: options.effects_volume,
         }
     }
 }
@@ -22,52 +41,210 @@ pub enum Input {
     PickResolution,
     SetWindowMode(WindowMode),
     PickWindowMode,
+    SetMonitor(usize),
+    PickMonitor,
+    SetGameSpeed(f32),
+    PickGameSpeed,
+    SetMulligan(Mulligan),
+    PickMulligan,
+    SetKey(GameAction, KeyCode),
+    RebindKey(GameAction),
+    PickKeyBindings,
+    ToggleFps,
+    SetMusicVolume(f32),
+    SetEffectsVolume(f32),
     Back,
 }
 
 impl Settings {
-    fn recalculate_resolutions(&mut self, cw: f32, ch: f32) {
+    fn clear_groups(&mut self) {
         self.resolutions.clear();
         self.modes.clear();
-        for &(w, h, text) in ALLOWED_RESOLUTIONS {
+        self.monitors.clear();
+        self.speeds.clear();
+        self.mulligans.clear();
+        self.bindings.clear();
+        self.rebinding = None;
+    }
+
+    fn recalculate_resolutions(&mut self, monitor: &Monitor, mode: WindowMode, cw: f32, ch: f32) {
+        self.clear_groups();
+        // A window as big as the monitor doesn't fit on it once the title
+        // bar and borders are added.
+        let fits = |w: f32, h: f32| match mode {
+            WindowMode::Windowed => w < monitor.width && h < monitor.height,
+            WindowMode::Borderless => w <= monitor.width && h <= monitor.height,
+        };
+        let mut resolutions = RESOLUTION_SCALES
+            .iter()
+            // Sizes are kept even, so that the window centres exactly.
+            .map(|&scale| ((monitor.width * scale / 2.0).round() * 2.0, (monitor.height * scale / 2.0).round() * 2.0))
+            .chain(COMMON_RESOLUTIONS.iter().copied())
+            .filter(|&(w, h)| w >= MIN_RESOLUTION_WIDTH && fits(w, h))
+            .collect::<Vec<_>>();
+        resolutions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        resolutions.dedup();
+        for (w, h) in resolutions {
             let selected = (cw - w).abs() <= 1.0 && (ch - h).abs() <= 1.0;
-            self.resolutions.push((w, h, text, selected));
+            self.resolutions.push((w, h, selected));
         }
     }
 
     fn recalculate_modes(&mut self, mode: WindowMode) {
-        self.resolutions.clear();
-        self.modes.clear();
+        self.clear_groups();
         for &(m, text) in ALLOWED_MODES {
             let selected = m == mode;
             self.modes.push((m, text, selected));
         }
     }
+
+    fn recalculate_speeds(&mut self, speed: f32) {
+        self.clear_groups();
+        for &(s, text) in GAME_SPEEDS {
+            let selected = (s - speed).abs() < 0.01;
+            self.speeds.push((s, text, selected));
+        }
+    }
+
+    fn recalculate_mulligans(&mut self, mulligan: Mulligan) {
+        self.clear_groups();
+        for &(m, text) in MULLIGAN_RULES {
+            let selected = m == mulligan;
+            self.mulligans.push((m, text, selected));
+        }
+    }
+
+    fn recalculate_bindings(&mut self, bindings: &KeyBindings) {
+        self.clear_groups();
+        for &action in GameAction::ALL {
+            self.bindings.push((action, bindings.key(action)));
+        }
+    }
+
+    fn recalculate_monitors(&mut self, monitors: Vec<Monitor>, current: &str) {
+        self.clear_groups();
+        for monitor in monitors {
+            let selected = monitor.name == current;
+            self.monitors.push((monitor, selected));
+        }
+    }
 }
 
 impl MenuSpec for Settings {
     type Input = Input;
 
-    fn update(&mut self, data: &GameData, ctx: &mut Ctx<'_>, input: Self::Input) -> Result<ViewChange> {
+    fn back(&self) -> Option<Self::Input> {
+        Some(Input::Back)
+    }
+
+    fn step(&self, input: &Self::Input, forward: bool) -> Option<Self::Input> {
+        match input {
+            Input::SetMusicVolume(_) => Some(Input::SetMusicVolume(next_volume(self.music_volume, forward, false))),
+            Input::SetEffectsVolume(_) => Some(Input::SetEffectsVolume(next_volume(self.effects_volume, forward, false))),
+            _ => None,
+        }
+    }
+
+    fn poll(&mut self, ctx: &Ctx<'_>) -> Option<Self::Input> {
+        let action = self.rebinding?;
+        // The back key cancels rebinding instead of being bound, through
+        // `back` like in every other menu.
+        if ctx.is_action_click(Action::Back) {
+            return None;
+        }
+        ctx.clicked_keys()
+            .find(|&key| bindings::key_name(key).is_some())
+            .map(|key| Input::SetKey(action, key))
+    }
+
+    fn update(&mut self, data: &mut GameData, ctx: &mut Ctx<'_>, input: Self::Input) -> Result<ViewChange> {
         match input {
             Input::SetResolution(w, h) => {
                 ctx.set_window_size(w, h)?;
-                self.recalculate_resolutions(w, h);
+                data.options.window_width = w;
+                data.options.window_height = h;
+                store(&data.options);
+                self.recalculate_resolutions(&ctx.monitor(), ctx.window_mode(), w, h);
             }
             Input::PickResolution => {
                 let (w, h) = ctx.window_size();
-                self.recalculate_resolutions(w, h);
+                self.recalculate_resolutions(&ctx.monitor(), ctx.window_mode(), w, h);
             }
             Input::SetWindowMode(mode) => {
                 ctx.set_window_mode(mode)?;
+                data.options.window_mode = mode;
+                store(&data.options);
                 self.recalculate_modes(mode);
             }
             Input::PickWindowMode => {
                 let mode = ctx.window_mode();
                 self.recalculate_modes(mode);
             }
+            Input::SetMonitor(index) => {
+                let name = self.monitors[index].0.name.clone();
+                ctx.set_monitor(Some(name.clone()))?;
+                data.options.monitor = Some(name);
+                store(&data.options);
+                self.recalculate_monitors(ctx.monitors(), &ctx.monitor().name);
+            }
+            Input::PickMonitor => {
+                self.recalculate_monitors(ctx.monitors(), &ctx.monitor().name);
+            }
+            Input::SetGameSpeed(speed) => {
+                data.options.game_speed = speed;
+                store(&data.options);
+                self.recalculate_speeds(speed);
+            }
+            Input::PickGameSpeed => {
+                self.recalculate_speeds(data.options.game_speed);
+            }
+            Input::SetMulligan(mulligan) => {
+                data.options.mulligan = mulligan;
+                store(&data.options);
+                self.recalculate_mulligans(mulligan);
+            }
+            Input::PickMulligan => {
+                self.recalculate_mulligans(data.options.mulligan);
+            }
+            Input::SetKey(action, key) => {
+                data.options.key_bindings.set_key(action, key);
+                ctx.set_input_map(data.options.key_bindings.input_map());
+                store(&data.options);
+                self.recalculate_bindings(&data.options.key_bindings);
+            }
+            Input::RebindKey(action) => {
+                self.rebinding = Some(action);
+            }
+            Input::PickKeyBindings => {
+                self.recalculate_bindings(&data.options.key_bindings);
+            }
+            Input::SetMusicVolume(volume) => {
+                self.clear_groups();
+                data.options.music_volume = volume;
+                ctx.audio().set_music_volume(volume);
+                store(&data.options);
+                self.music_volume = volume;
+            }
+            Input::SetEffectsVolume(volume) => {
+                self.clear_groups();
+                data.options.effects_volume = volume;
+                store(&data.options);
+                ctx.audio().set_effects_volume(volume);
+                ctx.audio().play_sound(sounds::COIN);
+                self.effects_volume = volume;
+            }
+            Input::ToggleFps => {
+                self.clear_groups();
+                data.options.show_fps = !data.options.show_fps;
+                ctx.set_debug_overlay(data.options.show_fps);
+                store(&data.options);
+                self.show_fps = data.options.show_fps;
+            }
+            Input::Back if self.rebinding.is_some() => {
+                self.rebinding = None;
+            }
             Input::Back => {
-                return Ok(ViewChange::Pop);
+                return Ok(ViewChange::Pop.with_transition(Transition::Slide));
             }
         }
         Ok(ViewChange::None)
@@ -77,14 +254,14 @@ impl MenuSpec for Settings {
         let mut buttons = Vec::new();
 
         buttons.push(ButtonSpec {
-            text: "Screen resolution",
-            state: if self.resolutions.len() > 0 { ButtonState::Selected } else { ButtonState::Normal },
+            text: "Screen resolution".into(),
+            state: if !self.resolutions.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
             on_click: Input::PickResolution,
             indent_level: 0,
         });
-        for &(w, h, text, selected) in &self.resolutions {
+        for &(w, h, selected) in &self.resolutions {
             buttons.push(ButtonSpec {
-                text,
+                text: format!("{} x {}", w, h),
                 state: if selected { ButtonState::Selected } else { ButtonState::Normal },
                 on_click: Input::SetResolution(w, h),
                 indent_level: 1,
@@ -92,14 +269,14 @@ impl MenuSpec for Settings {
         }
 
         buttons.push(ButtonSpec {
-            text: "Window mode",
-            state: if self.modes.len() > 0 { ButtonState::Selected } else { ButtonState::Normal },
+            text: "Window mode".into(),
+            state: if !self.modes.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
             on_click: Input::PickWindowMode,
             indent_level: 0,
         });
         for &(mode, text, selected) in &self.modes {
             buttons.push(ButtonSpec {
-                text,
+                text: text.into(),
                 state: if selected { ButtonState::Selected } else { ButtonState::Normal },
                 on_click: Input::SetWindowMode(mode),
                 indent_level: 1,
@@ -107,7 +284,95 @@ impl MenuSpec for Settings {
         }
 
         buttons.push(ButtonSpec {
-            text: "Back",
+            text: "Monitor".into(),
+            state: if !self.monitors.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
+            on_click: Input::PickMonitor,
+            indent_level: 0,
+        });
+        for (index, (monitor, selected)) in self.monitors.iter().enumerate() {
+            buttons.push(ButtonSpec {
+                text: format!("{} ({} x {})", monitor.name, monitor.width, monitor.height),
+                state: if *selected { ButtonState::Selected } else { ButtonState::Normal },
+                on_click: Input::SetMonitor(index),
+                indent_level: 1,
+            });
+        }
+
+        buttons.push(ButtonSpec {
+            text: "Game speed".into(),
+            state: if !self.speeds.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
+            on_click: Input::PickGameSpeed,
+            indent_level: 0,
+        });
+        for &(speed, text, selected) in &self.speeds {
+            buttons.push(ButtonSpec {
+                text: text.into(),
+                state: if selected { ButtonState::Selected } else { ButtonState::Normal },
+                on_click: Input::SetGameSpeed(speed),
+                indent_level: 1,
+            });
+        }
+
+        // Volumes are stepped with left and right, clicking steps up and
+        // wraps around to mute.
+        buttons.push(ButtonSpec {
+            text: format!("Music: {}", volume_text(self.music_volume)),
+            state: ButtonState::Normal,
+            on_click: Input::SetMusicVolume(next_volume(self.music_volume, true, true)),
+            indent_level: 0,
+        });
+        buttons.push(ButtonSpec {
+            text: format!("Effects: {}", volume_text(self.effects_volume)),
+            state: ButtonState::Normal,
+            on_click: Input::SetEffectsVolume(next_volume(self.effects_volume, true, true)),
+            indent_level: 0,
+        });
+
+        buttons.push(ButtonSpec {
+            text: "Card redraw".into(),
+            state: if !self.mulligans.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
+            on_click: Input::PickMulligan,
+            indent_level: 0,
+        });
+        for &(mulligan, text, selected) in &self.mulligans {
+            buttons.push(ButtonSpec {
+                text: text.into(),
+                state: if selected { ButtonState::Selected } else { ButtonState::Normal },
+                on_click: Input::SetMulligan(mulligan),
+                indent_level: 1,
+            });
+        }
+
+        buttons.push(ButtonSpec {
+            text: "Key bindings".into(),
+            state: if !self.bindings.is_empty() { ButtonState::Selected } else { ButtonState::Normal },
+            on_click: Input::PickKeyBindings,
+            indent_level: 0,
+        });
+        for &(action, key) in &self.bindings {
+            let rebinding = self.rebinding == Some(action);
+            let key = if rebinding {
+                "press a key"
+            } else {
+                bindings::key_name(key).unwrap_or("?")
+            };
+            buttons.push(ButtonSpec {
+                text: format!("{}: {}", action.label(), key),
+                state: if rebinding { ButtonState::Selected } else { ButtonState::Normal },
+                on_click: Input::RebindKey(action),
+                indent_level: 1,
+            });
+        }
+
+        buttons.push(ButtonSpec {
+            text: format!("Show FPS: {}", if self.show_fps { "On" } else { "Off" }),
+            state: ButtonState::Normal,
+            on_click: Input::ToggleFps,
+            indent_level: 0,
+        });
+
+        buttons.push(ButtonSpec {
+            text: "Back".into(),
             state: ButtonState::Normal,
             on_click: Input::Back,
             indent_level: 0,
@@ -116,15 +381,79 @@ impl MenuSpec for Settings {
     }
 }
 
-const ALLOWED_RESOLUTIONS: &[(f32, f32, &str)] = &[
-    (1280.0, 720.0, "1280 x 720"),
-    (1600.0, 900.0, "1600 x 900"),
-    (1920.0, 1080.0, "1920 x 1080"),
-    (2560.0, 1440.0, "2560 x 1440"),
-    (3840.0, 2160.0, "3840 x 2160"),
+/// Settings stay in effect even if they can't be saved, so a failed write
+/// is only reported.
+fn store(options: &Options) {
+    if let Err(e) = options.store() {
+        eprintln!("failed to save settings: {}", e);
+    }
+}
+
+const VOLUME_STEPS: u32 = 10;
+
+fn next_volume(volume: f32, up: bool, wrap: bool) -> f32 {
+    let step = (volume * VOLUME_STEPS as f32).round() as u32;
+    let step = match (up, step) {
+        (true, VOLUME_STEPS) if wrap => 0,
+        (true, VOLUME_STEPS) => VOLUME_STEPS,
+        (true, step) => step + 1,
+        (false, step) => step.saturating_sub(1),
+    };
+    step as f32 / VOLUME_STEPS as f32
+}
+
+/// Drawn as a bar, so the button reads like a slider.
+fn volume_text(volume: f32) -> String {
+    let filled = (volume * VOLUME_STEPS as f32).round() as usize;
+    format!(
+        "{}{} {}%",
+        "|".repeat(filled),
+        ".".repeat(VOLUME_STEPS as usize - filled),
+        (volume * 100.0).round(),
+    )
+}
+
+/// Window sizes offered as fractions of the monitor's own size, so they
+/// keep its aspect ratio.
+///
+/// The monitor's supported video modes can't be listed: winit 0.19 has no
+/// API for it, so these and `COMMON_RESOLUTIONS` stand in for them.
+const RESOLUTION_SCALES: &[f32] = &[1.0, 5.0 / 6.0, 3.0 / 4.0, 2.0 / 3.0, 1.0 / 2.0];
+
+/// Common 16:9, 16:10 and 21:9 sizes, offered when they fit the monitor.
+const COMMON_RESOLUTIONS: &[(f32, f32)] = &[
+    (1280.0, 720.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+    (2560.0, 1440.0),
+    (3840.0, 2160.0),
+    (1280.0, 800.0),
+    (1440.0, 900.0),
+    (1680.0, 1050.0),
+    (1920.0, 1200.0),
+    (2560.0, 1600.0),
+    (2560.0, 1080.0),
+    (3440.0, 1440.0),
+    (5120.0, 2160.0),
 ];
+const MIN_RESOLUTION_WIDTH: f32 = 800.0;
 
 const ALLOWED_MODES: &[(WindowMode, &str)] = &[
     (WindowMode::Windowed, "Windowed"),
     (WindowMode::Borderless, "Borderless fullscreen"),
 ];
+
+const GAME_SPEEDS: &[(f32, &str)] = &[
+    (0.5, "0.5x"),
+    (1.0, "1x"),
+    (1.5, "1.5x"),
+    (2.0, "2x"),
+    (3.0, "3x"),
+];
+
+const MULLIGAN_RULES: &[(Mulligan, &str)] = &[
+    (Mulligan::OncePerLevel, "Free, once per level"),
+    (Mulligan::Coins(1), "1 coin each time"),
+    (Mulligan::Coins(2), "2 coins each time"),
+    (Mulligan::Coins(3), "3 coins each time"),
+];
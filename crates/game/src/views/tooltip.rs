@@ -0,0 +1,138 @@
+use engine::{FrameRenderer, Result, ScreenText, SCREEN_HEIGHT, SCREEN_WIDTH};
+use engine::ggez::graphics::{self, Align, Rect};
+use crate::card::{BuffKind, Card, CardEffect, Creature};
+
+const CARD_WIDTH: f32 = 320.0;
+const CARD_HEIGHT: f32 = 448.0;
+const TEXT_WIDTH: f32 = 400.0;
+const PADDING: f32 = 10.0;
+const MARGIN: f32 = 10.0;
+
+pub fn describe_card(card: &Card) -> Vec<String> {
+    let mut lines = Vec::new();
+    describe_effect(&card.effect, &mut lines);
+    lines
+}
+
+pub fn describe_creature(creature: &Creature, attack: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(match creature.max_health {
+        Some(limit) => format!("[b]Health:[/b] [heart] {}/{}", creature.health, limit),
+        None => format!("[b]Health:[/b] [heart] {}", creature.health),
+    });
+    lines.push(format!("[b]Attack:[/b] [sword] {} ({} base)", attack, creature.attack));
+    if let Some(weapon) = &creature.weapon {
+        lines.push(format!("[b]Weapon:[/b] +{} attack, {} uses left", weapon.damage, weapon.durability));
+    }
+    for buff in &creature.buffs {
+        lines.push(describe_buff(&buff.kind));
+    }
+    lines
+}
+
+fn describe_buff(buff: &BuffKind) -> String {
+    match *buff {
+        BuffKind::NextAttackBonus { damage } => format!(
+            "[b]Next attack bonus:[/b] the next attack deals {} extra damage, then the bonus is used up.",
+            damage,
+        ),
+        BuffKind::AttackBonus { damage } => format!(
+            "[b]Attack bonus:[/b] every attack deals {} extra damage.",
+            damage,
+        ),
+    }
+}
+
+fn describe_effect(effect: &CardEffect, lines: &mut Vec<String>) {
+    match effect {
+        CardEffect::None => {}
+        CardEffect::Heal { health } => lines.push(format!(
+            "[b]Heal:[/b] restores {} health, but not above the maximum.",
+            health,
+        )),
+        CardEffect::Coins { amount } => lines.push(format!(
+            "[b]Coins:[/b] gives {} coins to spend in shops.",
+            amount,
+        )),
+        CardEffect::Attack { use_base: true, bonus: 0 } => lines.push(
+            "[b]Attack:[/b] hits the next enemy with your attack power. Uses up one-time bonuses and weapon durability.".to_owned(),
+        ),
+        CardEffect::Attack { use_base: true, bonus } => lines.push(format!(
+            "[b]Attack:[/b] hits the next enemy with your attack power plus {}. Uses up one-time bonuses and weapon durability.",
+            bonus,
+        )),
+        CardEffect::Attack { use_base: false, bonus } => lines.push(format!(
+            "[b]Strike:[/b] deals {} damage to the next enemy.",
+            bonus,
+        )),
+        CardEffect::HealEnemy { health } => lines.push(format!(
+            "[b]Heal enemy:[/b] the next enemy restores {} health.",
+            health,
+        )),
+        CardEffect::Buff(buff) => lines.push(describe_buff(&buff.kind)),
+        CardEffect::BossBuff(buff) => {
+            lines.push("[b]Boss bonus:[/b] the boss gets this when you reach the card.".to_owned());
+            lines.push(describe_buff(&buff.kind));
+        }
+        CardEffect::Weapon(weapon) => lines.push(format!(
+            "[b]Weapon:[/b] +{} attack for {} attacks. Replaces the weapon you are holding.",
+            weapon.damage,
+            weapon.durability,
+        )),
+        CardEffect::Enemy(creature) => {
+            lines.push("[b]Enemy:[/b] you trade blows with it until one of you falls.".to_owned());
+            if !creature.rewards.is_empty() {
+                lines.push("[b]Defeating it gives:[/b]".to_owned());
+                for reward in &creature.rewards {
+                    describe_effect(reward, lines);
+                }
+            }
+        }
+        CardEffect::Buy { price, effect } => {
+            lines.push(format!(
+                "[b]Shop:[/b] pay {} coins to get the effect below. Does nothing if you can't afford it.",
+                price,
+            ));
+            describe_effect(effect, lines);
+        }
+        CardEffect::Disarm => lines.push("[b]Disarm:[/b] removes the next trap on the path.".to_owned()),
+    }
+}
+
+/// Draws an enlarged card (if any) with the description next to it, placed
+/// beside `anchor` on whichever side has more room.
+pub fn draw(renderer: &mut FrameRenderer<'_>, anchor: Rect, card: Option<&Card>, lines: &[String]) -> Result {
+    let mut text = if lines.is_empty() {
+        None
+    } else {
+        Some(ScreenText::rich(lines.join("\n"), 28.0).with_bounds(TEXT_WIDTH, Align::Left))
+    };
+    let text_height = match &mut text {
+        Some(text) => renderer.text_dimensions(text).1 + PADDING * 2.0,
+        None => 0.0,
+    };
+    let card_width = if card.is_some() { CARD_WIDTH + MARGIN } else { 0.0 };
+    let text_width = if text.is_some() { TEXT_WIDTH + PADDING * 2.0 } else { 0.0 };
+    let width = card_width + text_width;
+    let height = if card.is_some() { text_height.max(CARD_HEIGHT) } else { text_height };
+
+    let x = if anchor.x + anchor.w / 2.0 < SCREEN_WIDTH / 2.0 {
+        anchor.x + anchor.w + MARGIN
+    } else {
+        anchor.x - MARGIN - width
+    };
+    let x = x.min(SCREEN_WIDTH - MARGIN - width).max(MARGIN);
+    let y = (anchor.y + anchor.h / 2.0 - height / 2.0)
+        .min(SCREEN_HEIGHT - MARGIN - height)
+        .max(MARGIN);
+
+    if let Some(card) = card {
+        renderer.draw(card.texture, x, y, CARD_WIDTH, CARD_HEIGHT)?;
+    }
+    if let Some(mut text) = text {
+        let x = x + card_width;
+        renderer.draw_shade(x, y, text_width, text_height, 0.85)?;
+        renderer.draw_text(&mut text, x + PADDING, y + PADDING, graphics::WHITE)?;
+    }
+    Ok(())
+}